@@ -1,3 +1,4 @@
+import json
 import logging
 import sys
 from pathlib import Path
@@ -40,13 +41,14 @@ def main():
     have_booking = False
     
     locations = ["141", "35", "34"]
+    test_type = sys.argv[1] if len(sys.argv) > 1 else "car"
     
     timeout_ms = 30000
     polling_ms = 500
     
     masked_user = f"{username[:4]}{'*' * (len(username) - 4)}"
     print(f"\n{'='*60}\nRTA Scraper Test\n{'='*60}")
-    print(f"Username: {masked_user} | Headless: {headless} | Locations: {locations}\n")
+    print(f"Username: {masked_user} | Headless: {headless} | Test type: {test_type} | Locations: {locations}\n")
     
     proxies_path = Path(__file__).parent.parent / "data" / "proxies.env"
     proxies = []
@@ -73,23 +75,29 @@ def main():
             timeout_ms=timeout_ms,
             proxies=proxies,
             parallel_browsers=1,
+            test_type=test_type,
         )
         
         print(f"\n{'='*60}\nRESULTS\n{'='*60}")
         
-        if not results:
+        payloads = results.get("payloads", {})
+        if not payloads:
             print("No results returned. Check the logs for errors.")
         
-        for location, data in results.items():
-            slots = data.get("slots", [])
+        for location, payload in payloads.items():
+            slots_data = (json.loads(payload) or {}).get("ajaxresult", {}).get("slots", {})
+            slots = slots_data.get("listTimeSlot", [])
             available = [s for s in slots if s.get("availability")]
             times = [s.get('startTime', '?') for s in available[:5]]
             extra = f" (+{len(available)-5} more)" if len(available) > 5 else ""
             
-            print(f"\n📍 {location}: {data.get('next_available_date', 'N/A')}")
+            print(f"\n📍 {location}: {slots_data.get('nextAvailableDate', 'N/A')}")
             print(f"   Slots: {len(available)}/{len(slots)} available")
             if times:
                 print(f"   Times: {', '.join(times)}{extra}")
+
+        for location, failure in results.get("failures", {}).items():
+            print(f"\n❌ {location}: {failure['category']} after {failure['duration_ms']}ms - {failure['message']}")
             
     except Exception as e:
         logger.exception("Scraper failed")
@@ -0,0 +1,36 @@
+use log::{debug, info};
+use std::fs;
+use std::path::Path;
+
+use crate::settings::Settings;
+
+/// Deletes the artifact directories of all but the latest `artifact_retention_runs` runs.
+/// Run directories are named from their start time, so sorting by name sorts by age.
+pub fn prune_artifacts(settings: &Settings) -> Result<(), String> {
+    let dir = Path::new(&settings.artifacts_dir);
+    if !dir.exists() {
+        debug!("No artifact directory to prune");
+        return Ok(());
+    }
+
+    let mut runs: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read artifact directory: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.path())
+        .collect();
+
+    if runs.len() <= settings.artifact_retention_runs {
+        return Ok(());
+    }
+
+    runs.sort();
+    let expired = runs.len() - settings.artifact_retention_runs;
+    for path in &runs[..expired] {
+        fs::remove_dir_all(path)
+            .map_err(|e| format!("Failed to remove '{}': {}", path.display(), e))?;
+    }
+
+    info!("Removed failure artifacts of {} old runs", expired);
+    Ok(())
+}
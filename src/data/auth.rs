@@ -0,0 +1,89 @@
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+/// Token-protected endpoints are off unless a token is configured, and always need
+/// `Authorization: Bearer <token>`.
+fn check_bearer_token(headers: &HeaderMap, token: &Option<String>) -> Result<(), StatusCode> {
+    let Some(expected) = token else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(provided) if constant_time_eq(provided.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Compares without stopping at the first differing byte, so the response time
+/// doesn't reveal how much of the token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Route layer for the operator and worker endpoints, with the group's token as its state.
+pub async fn require_bearer_token(
+    State(token): State<Option<String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Err(status) = check_bearer_token(request.headers(), &token) {
+        return status.into_response();
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(authorization).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn endpoints_are_off_without_a_token() {
+        let result = check_bearer_token(&headers("Bearer secret"), &None);
+        assert_eq!(result, Err(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn only_the_configured_token_is_accepted() {
+        let token = Some("secret".to_string());
+        assert_eq!(
+            check_bearer_token(&headers("Bearer secret"), &token),
+            Ok(())
+        );
+        assert_eq!(
+            check_bearer_token(&headers("Bearer secreT"), &token),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            check_bearer_token(&headers("Bearer secret2"), &token),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            check_bearer_token(&headers("secret"), &token),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            check_bearer_token(&HeaderMap::new(), &token),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+}
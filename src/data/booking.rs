@@ -1,21 +1,33 @@
-use log::{debug, error, info, warn};
-use serde::{Deserialize, Serialize};
-use std::cmp::Ordering;
+use log::{error, info, warn};
 use std::collections::{HashMap, HashSet};
-use std::fs::{self, File};
-use std::hash::{DefaultHasher, Hasher};
-use std::path::Path;
-use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::{Arc, OnceLock, RwLock};
 use std::time::{Duration, Instant};
 
-use super::discord::notify_scrape_blocked;
-use super::shared_booking::{BookingData, LocationBookings, TimeSlot};
+use super::artifacts::prune_artifacts;
+use super::circuit_breaker::{CircuitBreaker, RunPermission, Transition};
+use super::diff::{diff_booking_data, label_new_slots, LocationDiff};
+use super::discord::{notify_403_blocked, notify_circuit_breaker};
+use super::history::HistoryManager;
+use super::payloads;
+use super::proxy_pool::ProxyPool;
+use super::proxy_source::{self, ProxySource};
+use super::runs::{RunLog, ScrapeRun};
+use super::schedule::Scheduler;
+use super::scraper::{
+    create_scraper, BlockedProxy, FailureCategory, LocationFailure, ScrapeOutput, Scraper,
+};
+use super::shared_booking::{BookingData, LocationBookings, TestType, TimeSlot};
+use super::store::BookingStore;
 use crate::settings::Settings;
 
+/// Longest delay between two scrape attempts of a run.
+const RETRY_MAX_DELAY_SECS: u64 = 120;
+/// Attempts in a row where every location failed to log in before a run gives up.
+const MAX_LOGIN_FAILURES: u32 = 2;
+
 static BOOKING_DATA: OnceLock<Arc<RwLock<(BookingData, String)>>> = OnceLock::new();
 static BACKGROUND_RUNNING: OnceLock<Arc<RwLock<bool>>> = OnceLock::new();
-static PROXY_ROTATION_INDEX: AtomicUsize = AtomicUsize::new(0);
+static BOOKING_STORE: OnceLock<Box<dyn BookingStore>> = OnceLock::new();
 
 fn get_booking_data() -> &'static Arc<RwLock<(BookingData, String)>> {
     BOOKING_DATA.get_or_init(|| Arc::new(RwLock::new((BookingData::default(), String::new()))))
@@ -32,22 +44,21 @@ impl BookingManager {
         get_booking_data().read().unwrap().clone()
     }
 
-    pub fn get_location_data(location_id: String) -> Option<(LocationBookings, String)> {
+    pub fn get_location_data(
+        location_id: String,
+        test_type: TestType,
+    ) -> Option<(LocationBookings, String)> {
         Self::get_data()
             .0
-            .results
-            .iter()
-            .find(|booking| booking.location == location_id)
-            .and_then(|booking| Some((booking.clone(), booking.calculate_hash())))
+            .location(&location_id, test_type)
+            .map(|booking| (booking.clone(), booking.calculate_hash()))
     }
 
-    pub fn get_location_slots(location_code: &str) -> Option<Vec<TimeSlot>> {
+    pub fn get_location_slots(location_code: &str, test_type: TestType) -> Option<Vec<TimeSlot>> {
         let data_guard = get_booking_data().read().unwrap();
         data_guard
             .0
-            .results
-            .iter()
-            .find(|loc| loc.location == location_code)
+            .location(location_code, test_type)
             .map(|loc| loc.slots.clone())
     }
 
@@ -66,34 +77,29 @@ impl BookingManager {
         available
     }
 
-    pub fn init_from_file(file_path: &str) -> Result<(), String> {
-        if !Path::new(file_path).exists() {
-            debug!("No path for booking data");
-            return Ok(());
+    pub fn init_from_store(store: Box<dyn BookingStore>) -> Result<(), String> {
+        let loaded = store.load();
+
+        if BOOKING_STORE.set(store).is_err() {
+            return Err("Booking store already initialized".to_string());
         }
 
-        fs::read_to_string(file_path)
-            .map_err(|e| format!("Failed to read file: {}", e))
-            .and_then(|json_str| {
-                serde_json::from_str::<BookingData>(&json_str)
-                    .map_err(|e| format!("Failed to parse JSON: {}", e))
-                    .map(|data| {
-                        let hash = data.calculate_hash();
-                        let mut data_guard = get_booking_data().write().unwrap();
-                        *data_guard = (data, hash);
-                    })
-            })
+        if let Some(data) = loaded? {
+            let hash = data.calculate_hash();
+            let mut data_guard = get_booking_data().write().unwrap();
+            *data_guard = (data, hash);
+        }
+
+        Ok(())
     }
 
-    pub fn save_to_file(file_path: &str) -> Result<(), String> {
+    pub fn save_to_store() -> Result<(), String> {
+        let store = BOOKING_STORE
+            .get()
+            .ok_or_else(|| "Booking store not initialized".to_string())?;
         let data_guard = get_booking_data().read().unwrap();
 
-        serde_json::to_string_pretty(&data_guard.0)
-            .map_err(|e| format!("Failed to serialize data: {}", e))
-            .and_then(|json_str| {
-                fs::write(file_path, json_str)
-                    .map_err(|e| format!("Failed to write to file: {}", e))
-            })
+        store.save(&data_guard.0)
     }
 
     fn clean_data(results: Vec<LocationBookings>) -> Vec<LocationBookings> {
@@ -123,20 +129,70 @@ impl BookingManager {
         *data_guard = (cloned_results, new_hash_data);
     }
 
-    pub fn update_data(mut new_results: Vec<LocationBookings>) {
+    /// Merges freshly scraped `test_type` locations into the current data. Locations in
+    /// `attempted` but missing from `new_results` keep their previous slots and are marked
+    /// stale; locations and test types that weren't part of this run are left as they were.
+    /// Returns what changed.
+    pub fn update_data(
+        mut new_results: Vec<LocationBookings>,
+        test_type: TestType,
+        attempted: &[String],
+    ) -> Vec<LocationDiff> {
+        let now = chrono::Utc::now().to_rfc3339();
+
+        let mut data_guard = get_booking_data().write().unwrap();
+
         new_results = Self::clean_data(new_results);
+        for location in new_results.iter_mut() {
+            location.test_type = test_type;
+            let previous = data_guard.0.location(&location.location, test_type);
+            label_new_slots(previous, location);
+
+            location.last_scraped = Some(now.clone());
+            location.stale = false;
+        }
+
+        let scraped: HashSet<String> = new_results
+            .iter()
+            .map(|location| location.location.clone())
+            .collect();
+
+        let carried_over = data_guard
+            .0
+            .results
+            .iter()
+            .filter(|location| {
+                location.test_type != test_type || !scraped.contains(&location.location)
+            })
+            .cloned()
+            .map(|mut location| {
+                if location.test_type == test_type && attempted.contains(&location.location) {
+                    location.stale = true;
+                }
+                location
+            });
+
+        let mut merged: Vec<LocationBookings> = new_results.into_iter().chain(carried_over).collect();
+        merged.sort_by(|a, b| {
+            a.location
+                .cmp(&b.location)
+                .then(a.test_type.cmp(&b.test_type))
+        });
+
         let updated_data = BookingData {
-            results: new_results,
-            last_updated: Some(chrono::Utc::now().to_rfc3339()),
+            results: merged,
+            last_updated: Some(now),
         };
 
-        let hash = updated_data.calculate_hash();
+        let diffs = diff_booking_data(&data_guard.0, &updated_data);
 
-        let mut data_guard = get_booking_data().write().unwrap();
+        let hash = updated_data.calculate_hash();
         *data_guard = (updated_data, hash);
+
+        diffs
     }
 
-    pub fn start_background_updates(locations: Vec<String>, file_path: String, settings: Settings) {
+    pub fn start_background_updates(locations: Vec<String>, settings: Settings) {
         {
             let mut running = get_background_status().write().unwrap();
             if *running {
@@ -147,48 +203,216 @@ impl BookingManager {
 
         let running_status = Arc::clone(get_background_status());
 
-        tokio::spawn(async move {
-            let update_interval = Duration::from_secs(settings.scrape_refresh_time_min * 60);
+        let scraper = create_scraper(&settings);
+        info!("Using {} scraper backend", scraper.name());
 
+        let mut scheduler = match Scheduler::new(locations, &settings) {
+            Ok(scheduler) => scheduler,
+            Err(e) => {
+                error!("Background updates not started: {}", e);
+                *get_background_status().write().unwrap() = false;
+                return;
+            }
+        };
+
+        tokio::spawn(async move {
             if settings.initial_delay_hours > 0.0 {
                 let delay_secs = (settings.initial_delay_hours * 3600.0) as u64;
                 info!("Delaying first background update by {} hours ({} seconds)", settings.initial_delay_hours, delay_secs);
                 tokio::time::sleep(Duration::from_secs(delay_secs)).await;
             }
 
+            if !scheduler.runs_on_start() {
+                BookingManager::wait_for_next_run(&scheduler, &settings).await;
+            }
+
             while *running_status.read().unwrap() {
-                BookingManager::perform_update(locations.clone(), &file_path, settings.clone())
-                    .await;
+                let now = chrono::Utc::now();
+                let permission = CircuitBreaker::permission(now);
+
+                if let RunPermission::Wait(until) = permission {
+                    info!("Circuit breaker open until {}, skipping scrape", until);
+                    tokio::time::sleep((until - now).to_std().unwrap_or_default()).await;
+                    continue;
+                }
+
+                if scheduler.is_quiet(now) {
+                    info!("Within quiet hours, skipping scrape");
+                } else {
+                    for test_type in scheduler.test_types().to_vec() {
+                        let mut due = scheduler.due_locations(
+                            &settings,
+                            &BookingManager::get_data().0,
+                            test_type,
+                            now,
+                        );
+                        if permission == RunPermission::Probe {
+                            due.truncate(1);
+                        }
+
+                        if due.is_empty() {
+                            continue;
+                        }
+
+                        info!(
+                            "{} locations are due for a {} scrape",
+                            due.len(),
+                            test_type.as_str()
+                        );
+                        scheduler.mark_attempted(test_type, &due, now);
+                        let run = BookingManager::perform_update(
+                            due,
+                            test_type,
+                            settings.clone(),
+                            scraper.as_ref(),
+                        )
+                        .await;
+                        BookingManager::record_run_outcome(&settings, &run).await;
+
+                        // A probe is a single run; the next wake-up sees the breaker's verdict.
+                        if permission == RunPermission::Probe {
+                            break;
+                        }
+                    }
+                }
 
-                tokio::time::sleep(update_interval).await;
+                BookingManager::wait_for_next_run(&scheduler, &settings).await;
             }
         });
     }
 
+    async fn record_run_outcome(settings: &Settings, run: &ScrapeRun) {
+        let transition =
+            CircuitBreaker::record_run(settings, run.failure(settings), chrono::Utc::now());
+
+        let (opened, detail) = match transition {
+            Some(Transition::Opened { until, reason }) => (
+                true,
+                format!(
+                    "Scraping paused until {} after repeated failures: {}",
+                    until.with_timezone(&chrono_tz::Australia::Sydney),
+                    reason
+                ),
+            ),
+            Some(Transition::Closed) => (false, "Probe run succeeded, scraping resumed".to_string()),
+            None => return,
+        };
+
+        if let Some(webhook_url) = &settings.webhook_url {
+            if let Err(e) = notify_circuit_breaker(webhook_url, opened, &detail).await {
+                error!("Failed to send Discord circuit breaker notification: {}", e);
+            }
+        }
+    }
+
+    async fn wait_for_next_run(scheduler: &Scheduler, settings: &Settings) {
+        let now = chrono::Utc::now();
+        let next_run = scheduler.plan_next_run(settings, &Self::get_data().0, now);
+        info!(
+            "Next scrape planned for {}",
+            next_run.with_timezone(&chrono_tz::Australia::Sydney)
+        );
+        tokio::time::sleep((next_run - now).to_std().unwrap_or_default()).await;
+    }
+
     pub fn stop_background_updates() {
         let mut running = get_background_status().write().unwrap();
         *running = false;
     }
 
-    pub async fn perform_update(locations: Vec<String>, file_path: &str, settings: Settings) {
-        let start_time = Instant::now();
-        let max_retries = settings.retries;
+    pub fn select_proxies(settings: &Settings) -> Result<Vec<String>, String> {
+        let all_proxies = ProxySource::load(settings)
+            .map_err(|e| format!("Failed to load proxies: {}", e))?;
 
-        let proxy_index = PROXY_ROTATION_INDEX.fetch_add(settings.parallel_browsers, AtomicOrdering::Relaxed);
-        let proxies = match settings.get_proxies_from_index(proxy_index, settings.parallel_browsers) {
-            Ok(p) => p,
-            Err(e) => {
-                error!("Failed to read proxies from file: {}", e);
-                return;
-            }
-        };
-        
+        if all_proxies.is_empty() {
+            return Err("No proxies available".to_string());
+        }
+
+        let proxies = ProxyPool::select(&all_proxies, settings.parallel_browsers);
         if proxies.is_empty() {
-            error!("No proxies available in proxy file");
+            return Err("All proxies are quarantined".to_string());
+        }
+
+        Ok(proxies)
+    }
+
+    /// Logs fields the parser doesn't know and appends the raw payloads to the archive.
+    fn archive_payloads(
+        settings: &Settings,
+        run: &ScrapeRun,
+        test_type: TestType,
+        output: &ScrapeOutput,
+    ) {
+        if !output.unknown_fields.is_empty() {
+            warn!(
+                "Timeslots payloads contain fields we don't read: {:?}",
+                output.unknown_fields
+            );
+        }
+
+        let Some(path) = &settings.payload_archive_path else {
+            return;
+        };
+        if output.payloads.is_empty() {
             return;
         }
-        
-        info!("Using proxies starting from index {}: {:?}", proxy_index, proxies);
+        if let Err(e) = payloads::archive_payloads(path, &run.id, test_type, &output.payloads) {
+            error!("Failed to archive timeslots payloads to '{}': {}", path, e);
+        }
+    }
+
+    /// The scraper only reports which proxies were blocked, so every other proxy counts as a
+    /// success if the attempt returned anything and as a timeout otherwise.
+    pub fn record_proxy_outcomes(proxies: &[String], output: &ScrapeOutput, elapsed: Duration) {
+        for proxy in proxies {
+            if output.blocked_proxies.iter().any(|blocked| &blocked.proxy == proxy) {
+                ProxyPool::record_block(proxy);
+            } else if !output.bookings.is_empty() {
+                ProxyPool::record_success(proxy, elapsed.as_millis() as u64);
+            } else {
+                ProxyPool::record_timeout(proxy);
+            }
+        }
+    }
+
+    fn log_failures(failures: &HashMap<String, LocationFailure>) {
+        for (location, failure) in failures {
+            warn!(
+                "Location {} failed ({}): {}",
+                location,
+                failure.category.as_str(),
+                failure.message
+            );
+        }
+    }
+
+    async fn notify_blocked_proxies(settings: &Settings, blocked_proxies: &[BlockedProxy]) {
+        let Some(webhook_url) = &settings.webhook_url else {
+            return;
+        };
+
+        for blocked in blocked_proxies {
+            if let Err(e) = notify_403_blocked(
+                webhook_url,
+                &proxy_source::mask(&blocked.proxy),
+                blocked.status_code,
+                &blocked.response_body,
+            ).await {
+                error!("Failed to send Discord notification: {}", e);
+            }
+        }
+    }
+
+    pub async fn perform_update(
+        locations: Vec<String>,
+        test_type: TestType,
+        settings: Settings,
+        scraper: &dyn Scraper,
+    ) -> ScrapeRun {
+        let start_time = Instant::now();
+        let max_retries = settings.retries;
+        let mut run = ScrapeRun::start(scraper.name(), test_type, &locations);
+        let mut consecutive_login_failures = 0;
 
         let mut final_results: HashMap<String, LocationBookings> = HashMap::new();
         let mut remaining_locations = locations.clone();
@@ -206,20 +430,93 @@ impl BookingManager {
                 remaining_locations.len()
             );
 
-            match super::rta::scrape_rta_timeslots(remaining_locations.clone(), &settings, proxies.clone()).await {
-                Ok(result_map) => {
+            let proxies = if scraper.requires_proxies() {
+                match Self::select_proxies(&settings) {
+                    Ok(proxies) => {
+                        info!("Using proxies: {:?}", proxy_source::mask_all(&proxies));
+                        proxies
+                    }
+                    Err(e) => {
+                        error!("{}", e);
+                        break;
+                    }
+                }
+            } else {
+                Vec::new()
+            };
+
+            let attempt_start = Instant::now();
+            let attempt_started_at = chrono::Utc::now();
+
+            match scraper
+                .scrape(
+                    remaining_locations.clone(),
+                    test_type,
+                    &settings,
+                    proxies.clone(),
+                    run.artifact_dir(&settings, attempt),
+                )
+                .await
+            {
+                Ok(output) => {
+                    run.record_attempt(
+                        attempt,
+                        attempt_started_at,
+                        &remaining_locations,
+                        &proxies,
+                        Ok(&output),
+                    );
+
                     info!(
                         "Successfully scraped {}/{} locations in attempt {}.",
-                        result_map.len(),
+                        output.bookings.len(),
                         remaining_locations.len(),
                         attempt
                     );
 
-                    for (k, v) in result_map {
+                    Self::record_proxy_outcomes(&proxies, &output, attempt_start.elapsed());
+
+                    Self::archive_payloads(&settings, &run, test_type, &output);
+
+                    Self::notify_blocked_proxies(&settings, &output.blocked_proxies).await;
+
+                    Self::log_failures(&output.failures);
+
+                    let all_login_failures = output.bookings.is_empty()
+                        && !output.failures.is_empty()
+                        && output
+                            .failures
+                            .values()
+                            .all(|failure| failure.category == FailureCategory::LoginFailure);
+                    consecutive_login_failures = if all_login_failures {
+                        consecutive_login_failures + 1
+                    } else {
+                        0
+                    };
+
+                    let given_up: HashSet<String> = output
+                        .failures
+                        .iter()
+                        .filter(|(_, failure)| !failure.category.is_retryable())
+                        .map(|(location, _)| location.clone())
+                        .collect();
+
+                    for (k, mut v) in output.bookings {
+                        v.test_type = test_type;
                         final_results.insert(k.to_string(), v);
                     }
 
-                    remaining_locations.retain(|loc| !final_results.contains_key(loc));
+                    remaining_locations.retain(|loc| {
+                        !final_results.contains_key(loc) && !given_up.contains(loc)
+                    });
+
+                    if consecutive_login_failures >= MAX_LOGIN_FAILURES {
+                        error!(
+                            "Login failed on {} attempts in a row, not retrying. Check the credentials.",
+                            consecutive_login_failures
+                        );
+                        break;
+                    }
 
                     if remaining_locations.is_empty() {
                         info!(
@@ -240,27 +537,28 @@ impl BookingManager {
                         attempt, max_retries, e
                     );
 
+                    run.record_attempt(
+                        attempt,
+                        attempt_started_at,
+                        &remaining_locations,
+                        &proxies,
+                        Err(e.to_string()),
+                    );
+
+                    for proxy in &proxies {
+                        ProxyPool::record_timeout(proxy);
+                    }
+
                     if attempt == max_retries {
                         error!(
                             "Failed to scrape {} locations after {} attempts.",
                             remaining_locations.len(),
                             max_retries
                         );
-                        
-                        // notify when failed
-                        if let Some(webhook_url) = &settings.webhook_url {
-                            if let Err(e) = notify_scrape_blocked(
-                                webhook_url,
-                                remaining_locations.len(),
-                                max_retries,
-                            ).await {
-                                error!("Failed to send Discord blocked notification: {}", e);
-                            }
-                        }
-                        
+
                         if final_results.is_empty() {
                             error!("No data was successfully scraped. No update will be performed.");
-                            return;
+                            return Self::finish_run(run, &settings);
                         } else {
                             warn!(
                                 "Partial data collected. Successfully scraped {}/{} locations.",
@@ -272,19 +570,34 @@ impl BookingManager {
             }
 
             if attempt < max_retries && !remaining_locations.is_empty() {
-                tokio::time::sleep(Duration::from_secs(5)).await;
+                let backoff = (settings.retry_delay_secs << (attempt - 1).min(5)).min(RETRY_MAX_DELAY_SECS);
+                tokio::time::sleep(Duration::from_secs(backoff)).await;
             }
         }
 
         if !final_results.is_empty() {
             let all_results: Vec<LocationBookings> = final_results.into_values().collect();
-            Self::update_data(all_results);
+            let diffs = Self::update_data(all_results.clone(), test_type, &locations);
+            info!(
+                "{} locations changed since the previous scrape ({} events).",
+                diffs.len(),
+                diffs.iter().map(|diff| diff.events.len()).sum::<usize>()
+            );
+
+            let scraped_at = Self::get_data().0.last_updated.unwrap_or_default();
+            if let Err(e) = HistoryManager::append(Self::clean_data(all_results), scraped_at) {
+                error!(
+                    "Failed to append scrape to history '{}': {}",
+                    settings.history_path, e
+                );
+            }
         }
 
-        if let Err(e) = Self::save_to_file(file_path) {
+        if let Err(e) = Self::save_to_store() {
             error!(
-                "Failed to save booking data to file '{}': {}",
-                file_path, e
+                "Failed to save booking data to '{}': {}",
+                settings.storage_path(),
+                e
             );
         }
 
@@ -293,11 +606,36 @@ impl BookingManager {
         let seconds = elapsed.as_secs() % 60;
         let millis = elapsed.subsec_millis();
         info!(
-            "Total scraping time across all attempts: {}m {}s {}ms ({} locations)",
+            "Total {} scraping time across all attempts: {}m {}s {}ms ({} locations)",
+            test_type.as_str(),
             minutes,
             seconds,
             millis,
             locations.len()
         );
+
+        Self::finish_run(run, &settings)
+    }
+
+    fn finish_run(mut run: ScrapeRun, settings: &Settings) -> ScrapeRun {
+        run.finish();
+        if let Err(e) = RunLog::append(run.clone()) {
+            error!("Failed to record scrape run: {}", e);
+        }
+        if let Err(e) = prune_artifacts(settings) {
+            error!(
+                "Failed to prune artifacts in '{}': {}",
+                settings.artifacts_dir, e
+            );
+        }
+        if let (Some(path), Some(days)) = (
+            &settings.payload_archive_path,
+            settings.payload_retention_days,
+        ) {
+            if let Err(e) = payloads::prune_archive(path, days, chrono::Utc::now()) {
+                error!("Failed to prune payload archive '{}': {}", path, e);
+            }
+        }
+        run
     }
 }
@@ -0,0 +1,223 @@
+use axum::extract::{Path, Query};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{Duration, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Australia::Sydney;
+use serde::Deserialize;
+
+use super::booking::BookingManager;
+use super::location::{Location, LocationManager};
+use super::shared_booking::{LocationBookings, TestType, TimeSlot, SLOT_PARAM_FORMAT};
+
+/// Slots are 45 minutes apart at every centre, which is as close to a test length as we get.
+const SLOT_LENGTH_MINUTES: i64 = 45;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CalendarQuery {
+    pub locations: Option<String>,
+    pub slot: Option<String>,
+    /// Defaults to the car test.
+    pub test_type: Option<String>,
+}
+
+impl CalendarQuery {
+    fn test_type(&self) -> Option<TestType> {
+        match self.test_type.as_deref() {
+            Some(value) => TestType::parse(value),
+            None => Some(TestType::Car),
+        }
+    }
+}
+
+/// Stable across scrapes so calendar clients update events instead of duplicating them.
+/// Car slots keep the uids they had before other test types were tracked.
+pub fn slot_uid(location_id: &str, test_type: TestType, slot: &TimeSlot) -> String {
+    let start = slot.start_time.format(SLOT_PARAM_FORMAT);
+    match test_type {
+        TestType::Car => format!("{}-{}@nsw-drivers-test", location_id, start),
+        _ => format!(
+            "{}-{}-{}@nsw-drivers-test",
+            location_id,
+            test_type.as_str(),
+            start
+        ),
+    }
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds content lines longer than 75 octets as required by RFC 5545.
+fn push_line(out: &mut String, line: &str) {
+    let mut current_len = 0;
+    for ch in line.chars() {
+        if current_len + ch.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            current_len = 1;
+        }
+        out.push(ch);
+        current_len += ch.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn push_event(out: &mut String, location: &Location, test_type: TestType, slot: &TimeSlot) {
+    let ical_time = |time: chrono::DateTime<Utc>| time.format("%Y%m%dT%H%M%SZ").to_string();
+    let start = slot.start_time.with_timezone(&Utc);
+    let end = start + Duration::minutes(SLOT_LENGTH_MINUTES);
+
+    push_line(out, "BEGIN:VEVENT");
+    push_line(
+        out,
+        &format!("UID:{}", slot_uid(&location.id.to_string(), test_type, slot)),
+    );
+    push_line(out, &format!("DTSTAMP:{}", ical_time(Utc::now())));
+    push_line(out, &format!("DTSTART:{}", ical_time(start)));
+    push_line(out, &format!("DTEND:{}", ical_time(end)));
+    push_line(
+        out,
+        &format!(
+            "SUMMARY:{}",
+            escape_text(&format!("{} slot - {}", test_type.display(), location.name))
+        ),
+    );
+    push_line(
+        out,
+        &format!("LOCATION:{}", escape_text(&format!("Service NSW {}", location.name))),
+    );
+    push_line(
+        out,
+        &format!("GEO:{:.6};{:.6}", location.latitude, location.longitude),
+    );
+    push_line(
+        out,
+        &format!(
+            "DESCRIPTION:{}",
+            escape_text(&format!(
+                "Available {} slot at {} on {}. Book through myrta.com.",
+                test_type.display().to_lowercase(),
+                location.name,
+                slot.display()
+            ))
+        ),
+    );
+    push_line(out, "TRANSP:TRANSPARENT");
+    push_line(out, "END:VEVENT");
+}
+
+/// Builds a VCALENDAR with one VEVENT per available slot.
+pub fn build_calendar(
+    name: &str,
+    test_type: TestType,
+    entries: &[(Location, Vec<TimeSlot>)],
+) -> String {
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//nsw-drivers-test//availability//EN");
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+    push_line(&mut out, "METHOD:PUBLISH");
+    push_line(&mut out, &format!("X-WR-CALNAME:{}", escape_text(name)));
+    push_line(&mut out, "X-WR-TIMEZONE:Australia/Sydney");
+    push_line(&mut out, "REFRESH-INTERVAL;VALUE=DURATION:PT1H");
+    push_line(&mut out, "X-PUBLISHED-TTL:PT1H");
+
+    for (location, slots) in entries {
+        for slot in slots.iter().filter(|slot| slot.availability) {
+            push_event(&mut out, location, test_type, slot);
+        }
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+fn find_location(location_id: &str, test_type: TestType) -> Option<(Location, LocationBookings)> {
+    let location = LocationManager::new().get_by_id(location_id.parse().ok()?)?;
+    let (bookings, _) = BookingManager::get_location_data(location_id.to_string(), test_type)?;
+    Some((location, bookings))
+}
+
+fn ics_response(filename: &str, body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// `GET /api/calendar/{id}.ics`, optionally narrowed to a single slot with `?slot=` and for a
+/// test type other than the car test with `?test_type=`.
+pub async fn location_calendar(
+    Path(file): Path<String>,
+    Query(query): Query<CalendarQuery>,
+) -> Response {
+    let Some(location_id) = file.strip_suffix(".ics") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let Some(test_type) = query.test_type() else {
+        return (StatusCode::BAD_REQUEST, "Unknown test type").into_response();
+    };
+    let Some((location, bookings)) = find_location(location_id, test_type) else {
+        return (StatusCode::NOT_FOUND, "Location not found").into_response();
+    };
+
+    let mut slots = bookings.slots;
+
+    if let Some(slot_param) = &query.slot {
+        let Some(start_time) = NaiveDateTime::parse_from_str(slot_param, SLOT_PARAM_FORMAT)
+            .ok()
+            .and_then(|naive| Sydney.from_local_datetime(&naive).earliest())
+        else {
+            return (StatusCode::BAD_REQUEST, "Invalid slot").into_response();
+        };
+
+        slots.retain(|slot| slot.start_time == start_time);
+        if slots.is_empty() {
+            return (StatusCode::NOT_FOUND, "Slot is no longer available").into_response();
+        }
+
+        let calendar = build_calendar(&location.name, test_type, &[(location.clone(), slots)]);
+        return ics_response(&format!("{}-{}.ics", location_id, slot_param), calendar);
+    }
+
+    let name = format!("{}s - {}", test_type.display(), location.name);
+    let calendar = build_calendar(&name, test_type, &[(location, slots)]);
+    ics_response(&file, calendar)
+}
+
+/// `GET /api/calendar.ics?locations=1,2,3&test_type=car`
+pub async fn locations_calendar(Query(query): Query<CalendarQuery>) -> Response {
+    let Some(ids) = query.locations.as_deref() else {
+        return (StatusCode::BAD_REQUEST, "Missing locations").into_response();
+    };
+    let Some(test_type) = query.test_type() else {
+        return (StatusCode::BAD_REQUEST, "Unknown test type").into_response();
+    };
+
+    let entries: Vec<_> = ids
+        .split(',')
+        .map(str::trim)
+        .filter_map(|id| find_location(id, test_type))
+        .map(|(location, bookings)| (location, bookings.slots))
+        .collect();
+
+    if entries.is_empty() {
+        return (StatusCode::NOT_FOUND, "No matching locations").into_response();
+    }
+
+    let name = format!("{}s", test_type.display());
+    let calendar = build_calendar(&name, test_type, &entries);
+    ics_response("availability.ics", calendar)
+}
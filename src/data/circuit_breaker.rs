@@ -0,0 +1,279 @@
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::settings::Settings;
+
+static CIRCUIT_BREAKER: OnceLock<Arc<RwLock<BreakerStatus>>> = OnceLock::new();
+
+fn get_circuit_breaker() -> &'static Arc<RwLock<BreakerStatus>> {
+    CIRCUIT_BREAKER.get_or_init(|| Arc::new(RwLock::new(BreakerStatus::default())))
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    #[default]
+    Closed,
+    Open,
+    /// The backoff has passed and a probe run decides whether to close again.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    /// How many times in a row the breaker has opened, which sets the backoff.
+    pub consecutive_opens: u32,
+    pub open_until: Option<DateTime<Utc>>,
+    pub last_failure: Option<String>,
+    pub last_change: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunPermission {
+    Run,
+    /// Only a small probe run should go ahead.
+    Probe,
+    Wait(DateTime<Utc>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Transition {
+    Opened {
+        until: DateTime<Utc>,
+        reason: String,
+    },
+    Closed,
+}
+
+impl BreakerStatus {
+    fn permission(&mut self, now: DateTime<Utc>) -> RunPermission {
+        match self.state {
+            BreakerState::Closed => RunPermission::Run,
+            BreakerState::HalfOpen => RunPermission::Probe,
+            BreakerState::Open => match self.open_until {
+                Some(until) if until > now => RunPermission::Wait(until),
+                _ => {
+                    info!("Circuit breaker half-open, allowing a probe run");
+                    self.state = BreakerState::HalfOpen;
+                    self.last_change = Some(now);
+                    RunPermission::Probe
+                }
+            },
+        }
+    }
+
+    fn backoff(settings: &Settings, consecutive_opens: u32) -> Duration {
+        let minutes = (settings.breaker_backoff_min << consecutive_opens.saturating_sub(1).min(16))
+            .min(settings.breaker_max_backoff_min);
+        Duration::minutes(minutes as i64)
+    }
+
+    fn open(&mut self, settings: &Settings, reason: String, now: DateTime<Utc>) -> Transition {
+        self.consecutive_opens += 1;
+        let until = now + Self::backoff(settings, self.consecutive_opens);
+
+        warn!(
+            "Circuit breaker opened until {} ({}), attempt {} in a row",
+            until, reason, self.consecutive_opens
+        );
+
+        self.state = BreakerState::Open;
+        self.open_until = Some(until);
+        self.last_change = Some(now);
+
+        Transition::Opened { until, reason }
+    }
+
+    fn record_run(
+        &mut self,
+        settings: &Settings,
+        failure: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Option<Transition> {
+        match failure {
+            None => {
+                self.consecutive_failures = 0;
+                if self.state == BreakerState::Closed {
+                    return None;
+                }
+
+                info!("Circuit breaker closed after a successful probe run");
+                self.state = BreakerState::Closed;
+                self.consecutive_opens = 0;
+                self.open_until = None;
+                self.last_change = Some(now);
+                Some(Transition::Closed)
+            }
+            Some(reason) => {
+                self.consecutive_failures += 1;
+                self.last_failure = Some(reason.clone());
+
+                let should_open = match self.state {
+                    BreakerState::HalfOpen => true,
+                    _ => self.consecutive_failures >= settings.breaker_failure_threshold,
+                };
+
+                if should_open {
+                    Some(self.open(settings, reason, now))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Stops background scraping after repeated failed or heavily blocked runs, backing off
+/// exponentially between probe runs until one succeeds.
+pub struct CircuitBreaker;
+
+impl CircuitBreaker {
+    pub fn status() -> BreakerStatus {
+        get_circuit_breaker().read().unwrap().clone()
+    }
+
+    pub fn permission(now: DateTime<Utc>) -> RunPermission {
+        get_circuit_breaker().write().unwrap().permission(now)
+    }
+
+    /// Records how a run went. `failure` describes why the run counts as failed, if it did.
+    /// Returns the state change, if any, so the caller can notify about it.
+    pub fn record_run(
+        settings: &Settings,
+        failure: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Option<Transition> {
+        get_circuit_breaker()
+            .write()
+            .unwrap()
+            .record_run(settings, failure, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        serde_yaml::from_str(
+            "headless: true
+username: ''
+password: ''
+have_booking: false
+element_timeout: 1000
+retries: 1
+scrape_refresh_time_min: 240
+proxy_path: ''
+parallel_browsers: 1
+scraping_enabled: true
+breaker_failure_threshold: 3
+breaker_backoff_min: 10
+breaker_max_backoff_min: 60",
+        )
+        .unwrap()
+    }
+
+    fn start() -> DateTime<Utc> {
+        "2026-02-10T00:00:00Z".parse().unwrap()
+    }
+
+    fn fail(breaker: &mut BreakerStatus, now: DateTime<Utc>) -> Option<Transition> {
+        breaker.record_run(&settings(), Some("no data scraped".to_string()), now)
+    }
+
+    fn opened_until(transition: Option<Transition>) -> DateTime<Utc> {
+        match transition {
+            Some(Transition::Opened { until, .. }) => until,
+            other => panic!("expected the breaker to open, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn opens_after_the_failure_threshold() {
+        let mut breaker = BreakerStatus::default();
+        let now = start();
+
+        assert!(fail(&mut breaker, now).is_none());
+        assert!(fail(&mut breaker, now).is_none());
+        assert_eq!(breaker.permission(now), RunPermission::Run);
+
+        let until = opened_until(fail(&mut breaker, now));
+        assert_eq!(until, now + Duration::minutes(10));
+        assert_eq!(breaker.state, BreakerState::Open);
+        assert_eq!(breaker.permission(now), RunPermission::Wait(until));
+        assert_eq!(
+            breaker.permission(until - Duration::seconds(1)),
+            RunPermission::Wait(until)
+        );
+    }
+
+    #[test]
+    fn successes_reset_the_failure_count() {
+        let mut breaker = BreakerStatus::default();
+        let now = start();
+
+        fail(&mut breaker, now);
+        fail(&mut breaker, now);
+        assert!(breaker.record_run(&settings(), None, now).is_none());
+        assert_eq!(breaker.consecutive_failures, 0);
+        assert!(fail(&mut breaker, now).is_none());
+        assert_eq!(breaker.state, BreakerState::Closed);
+    }
+
+    #[test]
+    fn probes_once_the_backoff_has_passed() {
+        let mut breaker = BreakerStatus::default();
+        let now = start();
+        for _ in 0..3 {
+            fail(&mut breaker, now);
+        }
+        let until = breaker.open_until.unwrap();
+
+        assert_eq!(breaker.permission(until), RunPermission::Probe);
+        assert_eq!(breaker.state, BreakerState::HalfOpen);
+        assert_eq!(breaker.last_change, Some(until));
+        // Still half-open until the probe run is recorded.
+        assert_eq!(breaker.permission(until), RunPermission::Probe);
+
+        assert!(matches!(
+            breaker.record_run(&settings(), None, until),
+            Some(Transition::Closed)
+        ));
+        assert_eq!(breaker.state, BreakerState::Closed);
+        assert_eq!(breaker.consecutive_opens, 0);
+        assert_eq!(breaker.open_until, None);
+        assert_eq!(breaker.permission(until), RunPermission::Run);
+    }
+
+    #[test]
+    fn failed_probes_reopen_with_exponential_backoff() {
+        let mut breaker = BreakerStatus::default();
+        let mut now = start();
+        for _ in 0..3 {
+            fail(&mut breaker, now);
+        }
+
+        // 10, 20, 40, then capped at 60 minutes. A failed probe reopens straight away.
+        let mut backoffs = vec![breaker.open_until.unwrap() - now];
+        for _ in 0..3 {
+            now = breaker.open_until.unwrap();
+            assert_eq!(breaker.permission(now), RunPermission::Probe);
+            backoffs.push(opened_until(fail(&mut breaker, now)) - now);
+        }
+        assert_eq!(backoffs, [10, 20, 40, 60].map(Duration::minutes).to_vec());
+        assert_eq!(breaker.consecutive_opens, 4);
+
+        // Closing resets the backoff.
+        now = breaker.open_until.unwrap();
+        breaker.permission(now);
+        breaker.record_run(&settings(), None, now);
+        for _ in 0..3 {
+            fail(&mut breaker, now);
+        }
+        assert_eq!(breaker.open_until, Some(now + Duration::minutes(10)));
+    }
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::{Australia::Sydney, Tz};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use super::scraper::{ScrapeFuture, ScrapeOutput, Scraper};
+use super::shared_booking::{LocationBookings, TestType, TimeSlot};
+use crate::settings::Settings;
+
+/// Test slots run from 8:15 to 15:45, 45 minutes apart.
+const FIRST_SLOT_MINUTES: u32 = 8 * 60 + 15;
+const SLOTS_PER_DAY: u32 = 11;
+const SLOT_SPACING_MINUTES: u32 = 45;
+/// How many days past the first released day a centre shows at once.
+const BOOKING_WINDOW_DAYS: i64 = 28;
+
+struct DemoLocation {
+    /// Days from today until RTA's first released day, larger for busy centres.
+    lead_days: i64,
+    slots: BTreeSet<DateTime<Tz>>,
+}
+
+type DemoState = (StdRng, HashMap<(String, TestType), DemoLocation>);
+
+/// Generates believable, evolving availability for every requested location so the UI can
+/// run without credentials or proxies. Each call takes some slots, releases new days and
+/// occasionally drops in an early cancellation.
+pub struct DemoScraper {
+    state: Mutex<DemoState>,
+}
+
+impl Default for DemoScraper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DemoScraper {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new((StdRng::from_os_rng(), HashMap::new())),
+        }
+    }
+
+    fn slot_time(date: NaiveDate, index: u32) -> Option<DateTime<Tz>> {
+        let minutes = FIRST_SLOT_MINUTES + index * SLOT_SPACING_MINUTES;
+        let time = NaiveTime::from_hms_opt(minutes / 60, minutes % 60, 0)?;
+        Sydney.from_local_datetime(&date.and_time(time)).earliest()
+    }
+
+    fn is_test_day(date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+    }
+
+    /// Adds a sparse spread of slots for one day, as if RTA just opened it.
+    fn release_day(rng: &mut StdRng, slots: &mut BTreeSet<DateTime<Tz>>, date: NaiveDate) {
+        if !Self::is_test_day(date) {
+            return;
+        }
+        for index in 0..SLOTS_PER_DAY {
+            if rng.random_bool(0.35) {
+                if let Some(time) = Self::slot_time(date, index) {
+                    slots.insert(time);
+                }
+            }
+        }
+    }
+
+    fn new_location(rng: &mut StdRng, today: NaiveDate) -> DemoLocation {
+        let lead_days = rng.random_range(7..120);
+        let mut slots = BTreeSet::new();
+
+        let first_day = today + Duration::days(lead_days);
+        for offset in 0..BOOKING_WINDOW_DAYS {
+            if rng.random_bool(0.4) {
+                Self::release_day(rng, &mut slots, first_day + Duration::days(offset));
+            }
+        }
+
+        DemoLocation { lead_days, slots }
+    }
+
+    fn evolve(rng: &mut StdRng, location: &mut DemoLocation, now: DateTime<Tz>) {
+        let today = now.date_naive();
+
+        location.slots.retain(|slot| *slot > now);
+        location.slots.retain(|_| !rng.random_bool(0.15));
+
+        let horizon = today + Duration::days(location.lead_days + BOOKING_WINDOW_DAYS);
+        let latest = location.slots.last().map(|slot| slot.date_naive());
+        if latest.is_none_or(|latest| latest < horizon) && rng.random_bool(0.5) {
+            let date = latest.unwrap_or(today) + Duration::days(rng.random_range(1..4));
+            Self::release_day(rng, &mut location.slots, date);
+        }
+
+        if rng.random_bool(0.1) {
+            let earliest_day = location
+                .slots
+                .first()
+                .map(|slot| (slot.date_naive() - today).num_days())
+                .unwrap_or(location.lead_days)
+                .max(2);
+            let date = today + Duration::days(rng.random_range(1..earliest_day));
+            if let Some(time) = Self::slot_time(date, rng.random_range(0..SLOTS_PER_DAY)) {
+                if Self::is_test_day(date) {
+                    location.slots.insert(time);
+                }
+            }
+        }
+    }
+
+    fn generate(&self, locations: &[String], test_type: TestType) -> ScrapeOutput {
+        let now = Utc::now().with_timezone(&Sydney);
+        let mut state = self.state.lock().unwrap();
+        let (rng, demo_locations) = &mut *state;
+
+        let mut output = ScrapeOutput::default();
+        for location_id in locations {
+            let key = (location_id.clone(), test_type);
+            match demo_locations.get_mut(&key) {
+                Some(location) => Self::evolve(rng, location, now),
+                None => {
+                    let location = Self::new_location(rng, now.date_naive());
+                    demo_locations.insert(key.clone(), location);
+                }
+            }
+            let location = &demo_locations[&key];
+
+            let slots: Vec<TimeSlot> = location
+                .slots
+                .iter()
+                .enumerate()
+                .map(|(idx, start_time)| TimeSlot {
+                    availability: true,
+                    slot_number: Some(idx as u32 + 1),
+                    start_time: *start_time,
+                    label: None,
+                })
+                .collect();
+
+            output.bookings.insert(
+                location_id.clone(),
+                LocationBookings {
+                    location: location_id.clone(),
+                    test_type,
+                    next_available_date: slots.first().map(|slot| slot.display()),
+                    slots,
+                    last_scraped: None,
+                    stale: false,
+                },
+            );
+        }
+
+        output
+    }
+}
+
+impl Scraper for DemoScraper {
+    fn name(&self) -> &'static str {
+        "demo"
+    }
+
+    fn requires_proxies(&self) -> bool {
+        false
+    }
+
+    fn scrape<'a>(
+        &'a self,
+        locations: Vec<String>,
+        test_type: TestType,
+        _settings: &'a Settings,
+        _proxies: Vec<String>,
+        _artifact_dir: Option<String>,
+    ) -> ScrapeFuture<'a> {
+        Box::pin(async move { Ok(self.generate(&locations, test_type)) })
+    }
+}
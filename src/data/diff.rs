@@ -0,0 +1,354 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+use super::shared_booking::{BookingData, LocationBookings, SlotLabel, TestType, TimeSlot};
+
+/// A change to a single location's availability between two scrapes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlotEvent {
+    SlotAdded { slot: TimeSlot },
+    SlotRemoved { slot: TimeSlot },
+    EarliestMovedEarlier { previous: TimeSlot, current: TimeSlot },
+    EarliestMovedLater { previous: TimeSlot, current: TimeSlot },
+    AvailabilityGained { earliest: TimeSlot },
+    AvailabilityLost { previous_earliest: TimeSlot },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocationDiff {
+    pub location: String,
+    #[serde(default)]
+    pub test_type: TestType,
+    pub events: Vec<SlotEvent>,
+}
+
+fn available_slots(bookings: Option<&LocationBookings>) -> BTreeSet<TimeSlot> {
+    bookings
+        .map(|loc| {
+            loc.slots
+                .iter()
+                .filter(|slot| slot.availability)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Compares one location across two scrapes. Either side may be missing, which is treated
+/// as having no availability.
+pub fn diff_location(
+    previous: Option<&LocationBookings>,
+    current: Option<&LocationBookings>,
+) -> Vec<SlotEvent> {
+    let before = available_slots(previous);
+    let after = available_slots(current);
+
+    let mut events: Vec<SlotEvent> = after
+        .difference(&before)
+        .map(|slot| SlotEvent::SlotAdded { slot: slot.clone() })
+        .chain(
+            before
+                .difference(&after)
+                .map(|slot| SlotEvent::SlotRemoved { slot: slot.clone() }),
+        )
+        .collect();
+
+    match (before.first(), after.first()) {
+        (None, Some(earliest)) => events.push(SlotEvent::AvailabilityGained {
+            earliest: earliest.clone(),
+        }),
+        (Some(previous_earliest), None) => events.push(SlotEvent::AvailabilityLost {
+            previous_earliest: previous_earliest.clone(),
+        }),
+        (Some(previous), Some(current)) if current < previous => {
+            events.push(SlotEvent::EarliestMovedEarlier {
+                previous: previous.clone(),
+                current: current.clone(),
+            })
+        }
+        (Some(previous), Some(current)) if current > previous => {
+            events.push(SlotEvent::EarliestMovedLater {
+                previous: previous.clone(),
+                current: current.clone(),
+            })
+        }
+        _ => {}
+    }
+
+    events
+}
+
+/// Labels slots in `current` that were not available in `previous`. A new slot earlier than
+/// the previous earliest slot is almost always someone cancelling, anything else is treated
+/// as RTA releasing more slots. Locations seen for the first time are left unlabelled.
+pub fn label_new_slots(previous: Option<&LocationBookings>, current: &mut LocationBookings) {
+    let Some(previous) = previous else {
+        return;
+    };
+
+    let before = available_slots(Some(previous));
+    let previous_earliest = before.first();
+
+    for slot in current.slots.iter_mut() {
+        slot.label = if !slot.availability || before.contains(slot) {
+            None
+        } else if previous_earliest.is_some_and(|earliest| *slot < *earliest) {
+            Some(SlotLabel::LikelyCancellation)
+        } else {
+            Some(SlotLabel::NewRelease)
+        };
+    }
+}
+
+/// Diffs every location and test type present in either snapshot. Locations that were not
+/// scraped in `current` (marked stale) are skipped, since their slots are only carried over.
+pub fn diff_booking_data(previous: &BookingData, current: &BookingData) -> Vec<LocationDiff> {
+    let previous_by_id: HashMap<(&str, TestType), &LocationBookings> = previous
+        .results
+        .iter()
+        .map(|loc| ((loc.location.as_str(), loc.test_type), loc))
+        .collect();
+    let current_by_id: HashMap<(&str, TestType), &LocationBookings> = current
+        .results
+        .iter()
+        .map(|loc| ((loc.location.as_str(), loc.test_type), loc))
+        .collect();
+
+    let location_ids: BTreeSet<(&str, TestType)> = previous_by_id
+        .keys()
+        .chain(current_by_id.keys())
+        .copied()
+        .collect();
+
+    location_ids
+        .into_iter()
+        .filter(|id| !current_by_id.get(id).is_some_and(|loc| loc.stale))
+        .filter_map(|id| {
+            let events = diff_location(
+                previous_by_id.get(&id).copied(),
+                current_by_id.get(&id).copied(),
+            );
+            (!events.is_empty()).then(|| LocationDiff {
+                location: id.0.to_string(),
+                test_type: id.1,
+                events,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::shared_booking::parse_slot_time;
+
+    const A: &str = "20/02/2026 9:05";
+    const B: &str = "20/02/2026 10:50";
+    const C: &str = "03/03/2026 14:15";
+    const A_TAKEN: &str = "!20/02/2026 9:05";
+    const B_TAKEN: &str = "!20/02/2026 10:50";
+    const C_TAKEN: &str = "!03/03/2026 14:15";
+
+    fn slot(start_time: &str) -> TimeSlot {
+        TimeSlot {
+            availability: true,
+            slot_number: None,
+            start_time: parse_slot_time(start_time).unwrap(),
+            label: None,
+        }
+    }
+
+    /// Slots prefixed with `!` are listed as unavailable.
+    fn bookings(location: &str, test_type: TestType, slots: &[&str]) -> LocationBookings {
+        LocationBookings {
+            location: location.to_string(),
+            test_type,
+            slots: slots
+                .iter()
+                .map(|value| match value.strip_prefix('!') {
+                    Some(value) => TimeSlot {
+                        availability: false,
+                        ..slot(value)
+                    },
+                    None => slot(value),
+                })
+                .collect(),
+            next_available_date: None,
+            last_scraped: None,
+            stale: false,
+        }
+    }
+
+    #[test]
+    fn diffs_one_location() {
+        use SlotEvent::*;
+
+        let cases: Vec<(&str, Option<&[&str]>, Option<&[&str]>, Vec<SlotEvent>)> = vec![
+            ("both missing", None, None, vec![]),
+            ("unchanged", Some(&[A, B]), Some(&[A, B]), vec![]),
+            (
+                "first availability",
+                None,
+                Some(&[A]),
+                vec![
+                    SlotAdded { slot: slot(A) },
+                    AvailabilityGained { earliest: slot(A) },
+                ],
+            ),
+            (
+                "location disappeared",
+                Some(&[A]),
+                None,
+                vec![
+                    SlotRemoved { slot: slot(A) },
+                    AvailabilityLost {
+                        previous_earliest: slot(A),
+                    },
+                ],
+            ),
+            (
+                "earlier slot appeared",
+                Some(&[B]),
+                Some(&[A, B]),
+                vec![
+                    SlotAdded { slot: slot(A) },
+                    EarliestMovedEarlier {
+                        previous: slot(B),
+                        current: slot(A),
+                    },
+                ],
+            ),
+            (
+                "earliest slot taken",
+                Some(&[A, B]),
+                Some(&[B]),
+                vec![
+                    SlotRemoved { slot: slot(A) },
+                    EarliestMovedLater {
+                        previous: slot(A),
+                        current: slot(B),
+                    },
+                ],
+            ),
+            (
+                "later slot released",
+                Some(&[A]),
+                Some(&[A, C]),
+                vec![SlotAdded { slot: slot(C) }],
+            ),
+            (
+                "unavailable slot listed",
+                Some(&[A]),
+                Some(&[A, B_TAKEN]),
+                vec![],
+            ),
+            (
+                "slot became unavailable",
+                Some(&[A, B]),
+                Some(&[A_TAKEN, B]),
+                vec![
+                    SlotRemoved { slot: slot(A) },
+                    EarliestMovedLater {
+                        previous: slot(A),
+                        current: slot(B),
+                    },
+                ],
+            ),
+        ];
+
+        for (name, previous, current, expected) in cases {
+            let previous = previous.map(|slots| bookings("21", TestType::Car, slots));
+            let current = current.map(|slots| bookings("21", TestType::Car, slots));
+            assert_eq!(
+                diff_location(previous.as_ref(), current.as_ref()),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn labels_new_slots() {
+        let cancellation = Some(SlotLabel::LikelyCancellation);
+        let release = Some(SlotLabel::NewRelease);
+
+        let cases: Vec<(&str, Option<&[&str]>, &[&str], Vec<Option<SlotLabel>>)> = vec![
+            ("first seen", None, &[A, B], vec![None, None]),
+            (
+                "before and after the earliest",
+                Some(&[B]),
+                &[A, B, C],
+                vec![cancellation, None, release],
+            ),
+            (
+                "unavailable slots",
+                Some(&[B]),
+                &[A_TAKEN, C_TAKEN],
+                vec![None, None],
+            ),
+            (
+                "no previous availability",
+                Some(&[B_TAKEN]),
+                &[A, B],
+                vec![release, release],
+            ),
+        ];
+
+        for (name, previous, current, expected) in cases {
+            let previous = previous.map(|slots| bookings("21", TestType::Car, slots));
+            let mut current = bookings("21", TestType::Car, current);
+            label_new_slots(previous.as_ref(), &mut current);
+            let labels: Vec<_> = current.slots.iter().map(|slot| slot.label).collect();
+            assert_eq!(labels, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn relabels_slots_that_were_already_seen() {
+        let previous = bookings("21", TestType::Car, &[A]);
+        let mut current = bookings("21", TestType::Car, &[A]);
+        current.slots[0].label = Some(SlotLabel::NewRelease);
+        label_new_slots(Some(&previous), &mut current);
+        assert_eq!(current.slots[0].label, None);
+    }
+
+    #[test]
+    fn diffs_every_location_and_test_type() {
+        let previous = BookingData {
+            results: vec![
+                bookings("21", TestType::Car, &[A]),
+                bookings("21", TestType::Rider, &[A]),
+                bookings("68", TestType::Car, &[B]),
+                bookings("96", TestType::Car, &[A]),
+            ],
+            last_updated: None,
+        };
+        let mut stale = bookings("68", TestType::Car, &[]);
+        stale.stale = true;
+        let current = BookingData {
+            results: vec![
+                bookings("21", TestType::Car, &[A, C]),
+                bookings("21", TestType::Rider, &[A]),
+                stale,
+                bookings("99", TestType::Car, &[B]),
+            ],
+            last_updated: None,
+        };
+
+        let changed: Vec<(String, TestType, usize)> = diff_booking_data(&previous, &current)
+            .into_iter()
+            .map(|diff| (diff.location, diff.test_type, diff.events.len()))
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                // 21's rider slots are unchanged and 68 wasn't scraped this time.
+                ("21".to_string(), TestType::Car, 1),
+                ("96".to_string(), TestType::Car, 2),
+                ("99".to_string(), TestType::Car, 2),
+            ]
+        );
+    }
+}
@@ -39,22 +39,27 @@ pub async fn notify_403_blocked(
     Ok(())
 }
 
-pub async fn notify_scrape_blocked(
+pub async fn notify_circuit_breaker(
     webhook_url: &str,
-    failed_locations: usize,
-    max_retries: u64,
+    opened: bool,
+    detail: &str,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     let ts = Utc::now().to_rfc3339();
 
+    let (content, title, color) = if opened {
+        ("@everyone", "Scraping Paused", 15158332)
+    } else {
+        ("", "Scraping Resumed", 3066993)
+    };
+
     let payload = json!({
-        "content": "@everyone",
+        "content": content,
         "embeds": [{
-            "title": "Scraping Blocked",
-            "description": "<@here>",
-            "color": 15158332,
+            "title": title,
+            "description": "",
+            "color": color,
             "fields": [
-                {"name": "Status", "value": format!("Scraping failed after {} attempts", max_retries)},
-                {"name": "Failed Locations", "value": failed_locations.to_string()}
+                {"name": "Status", "value": detail}
             ],
             "timestamp": ts
         }],
@@ -69,6 +74,6 @@ pub async fn notify_scrape_blocked(
         .await?
         .error_for_status()?;
 
-    log::info!("Discord notification sent for scraping blocked after {} retries", max_retries);
+    log::info!("Discord notification sent for circuit breaker: {}", title);
     Ok(())
 }
@@ -0,0 +1,195 @@
+use axum::extract::Query;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::booking::BookingManager;
+use super::location::LocationManager;
+use super::shared_booking::{BookingData, TestType};
+
+/// Query string accepted by the export endpoints, all fields optional.
+///
+/// `locations` is a comma separated list of location IDs, `from`/`to` are inclusive
+/// `YYYY-MM-DD` dates, `test_type` is one of `car`, `rider` or `heavy_vehicle` and
+/// `max_distance_km` only applies when `lat` and `lng` are given.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportFilter {
+    pub locations: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub test_type: Option<String>,
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+    pub max_distance_km: Option<f64>,
+}
+
+impl ExportFilter {
+    pub fn location_ids(&self) -> Option<HashSet<String>> {
+        self.locations.as_ref().map(|ids| {
+            ids.split(',')
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .collect()
+        })
+    }
+
+    fn test_type(&self) -> Result<Option<TestType>, String> {
+        self.test_type
+            .as_deref()
+            .map(|value| {
+                TestType::parse(value).ok_or_else(|| format!("Unknown test type '{}'", value))
+            })
+            .transpose()
+    }
+
+    fn date_range(&self) -> Result<(Option<NaiveDate>, Option<NaiveDate>), String> {
+        let parse = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|date| {
+                    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .map_err(|e| format!("Invalid date '{}': {}", date, e))
+                })
+                .transpose()
+        };
+        Ok((parse(&self.from)?, parse(&self.to)?))
+    }
+}
+
+/// One available slot joined with its location metadata.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportRow {
+    pub location_id: String,
+    pub location_name: String,
+    pub test_type: TestType,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub pass_rate: f64,
+    pub distance_km: Option<f64>,
+    pub start_time: String,
+    pub date: String,
+    pub time: String,
+    pub slot_number: Option<u32>,
+    pub label: Option<String>,
+    pub last_scraped: Option<String>,
+    pub stale: bool,
+}
+
+pub fn export_rows(data: &BookingData, filter: &ExportFilter) -> Result<Vec<ExportRow>, String> {
+    let location_manager = LocationManager::new();
+    let location_ids = filter.location_ids();
+    let (from, to) = filter.date_range()?;
+    let test_type = filter.test_type()?;
+    let origin = filter.lat.zip(filter.lng);
+
+    let mut rows = Vec::new();
+
+    for bookings in &data.results {
+        if location_ids
+            .as_ref()
+            .is_some_and(|ids| !ids.contains(&bookings.location))
+        {
+            continue;
+        }
+
+        if test_type.is_some_and(|test_type| test_type != bookings.test_type) {
+            continue;
+        }
+
+        let Some(location) = bookings
+            .location
+            .parse::<u32>()
+            .ok()
+            .and_then(|id| location_manager.get_by_id(id))
+        else {
+            continue;
+        };
+
+        let distance_km = origin.map(|(lat, lng)| location.distance_from(lat, lng));
+        if let (Some(distance), Some(max_distance)) = (distance_km, filter.max_distance_km) {
+            if distance > max_distance {
+                continue;
+            }
+        }
+
+        let mut slots: Vec<_> = bookings
+            .slots
+            .iter()
+            .filter(|slot| slot.availability)
+            .filter(|slot| from.is_none_or(|from| slot.date() >= from))
+            .filter(|slot| to.is_none_or(|to| slot.date() <= to))
+            .collect();
+        slots.sort();
+
+        for slot in slots {
+            rows.push(ExportRow {
+                location_id: bookings.location.clone(),
+                location_name: location.name.clone(),
+                test_type: bookings.test_type,
+                latitude: location.latitude,
+                longitude: location.longitude,
+                pass_rate: location.pass_rate,
+                distance_km,
+                start_time: slot.start_time.to_rfc3339(),
+                date: slot.start_time.format("%Y-%m-%d").to_string(),
+                time: slot.start_time.format("%H:%M").to_string(),
+                slot_number: slot.slot_number,
+                label: slot.label.map(|label| label.as_str().to_string()),
+                last_scraped: bookings.last_scraped.clone(),
+                stale: bookings.stale,
+            });
+        }
+    }
+
+    Ok(rows)
+}
+
+pub fn rows_to_csv(rows: &[ExportRow]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer
+            .serialize(row)
+            .map_err(|e| format!("Failed to write CSV row: {}", e))?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| format!("Failed to finish CSV: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("CSV was not valid UTF-8: {}", e))
+}
+
+fn download(content_type: &'static str, filename: &str, body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+pub async fn export_csv(Query(filter): Query<ExportFilter>) -> Response {
+    let (data, _) = BookingManager::get_data();
+    match export_rows(&data, &filter).and_then(|rows| rows_to_csv(&rows)) {
+        Ok(csv) => download("text/csv; charset=utf-8", "availability.csv", csv),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+pub async fn export_json(Query(filter): Query<ExportFilter>) -> Response {
+    let (data, _) = BookingManager::get_data();
+    let json = export_rows(&data, &filter).and_then(|rows| {
+        serde_json::to_string_pretty(&rows).map_err(|e| format!("Failed to serialize: {}", e))
+    });
+
+    match json {
+        Ok(json) => download("application/json", "availability.json", json),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
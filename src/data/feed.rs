@@ -0,0 +1,237 @@
+use axum::extract::Query;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::diff::{diff_location, SlotEvent};
+use super::history::HistoryManager;
+use super::location::LocationManager;
+use super::shared_booking::{LocationBookings, TestType, TimeSlot};
+
+/// How far back the feed looks through the scrape history.
+const FEED_WINDOW_DAYS: i64 = 14;
+const MAX_FEED_ENTRIES: usize = 200;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct FeedQuery {
+    pub locations: Option<String>,
+    pub test_type: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EarlierSlotEntry {
+    pub location: String,
+    pub test_type: TestType,
+    pub scraped_at: String,
+    pub current: TimeSlot,
+    pub previous: Option<TimeSlot>,
+}
+
+/// Walks the scrape history and collects every time a location's earliest slot moved earlier,
+/// newest first. A location gaining availability after having none counts as well.
+pub fn earlier_slot_entries(
+    location_ids: Option<&[String]>,
+    test_type: Option<TestType>,
+) -> Vec<EarlierSlotEntry> {
+    let since = Utc::now() - Duration::days(FEED_WINDOW_DAYS);
+    let mut last_seen: HashMap<(String, TestType), LocationBookings> = HashMap::new();
+    let mut entries = Vec::new();
+
+    HistoryManager::for_each_since(since, |history_entry| {
+        for bookings in &history_entry.results {
+            if location_ids.is_some_and(|ids| !ids.contains(&bookings.location))
+                || test_type.is_some_and(|test_type| test_type != bookings.test_type)
+            {
+                continue;
+            }
+
+            let key = (bookings.location.clone(), bookings.test_type);
+            if let Some(previous) = last_seen.get(&key) {
+                for event in diff_location(Some(previous), Some(bookings)) {
+                    let (current, previous) = match event {
+                        SlotEvent::EarliestMovedEarlier { previous, current } => {
+                            (current, Some(previous))
+                        }
+                        SlotEvent::AvailabilityGained { earliest } => (earliest, None),
+                        _ => continue,
+                    };
+
+                    entries.push(EarlierSlotEntry {
+                        location: bookings.location.clone(),
+                        test_type: bookings.test_type,
+                        scraped_at: history_entry.scraped_at.clone(),
+                        current,
+                        previous,
+                    });
+                }
+            }
+
+            last_seen.insert(key, bookings.clone());
+        }
+    });
+
+    entries.reverse();
+    entries.truncate(MAX_FEED_ENTRIES);
+    entries
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub fn build_atom_feed(site_url: &str, entries: &[EarlierSlotEntry]) -> String {
+    let location_manager = LocationManager::new();
+    let site_url = site_url.trim_end_matches('/');
+    let updated = entries
+        .first()
+        .map(|entry| entry.scraped_at.clone())
+        .unwrap_or_else(|| Utc::now().to_rfc3339());
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str("  <title>NSW driving tests - earlier slots</title>\n");
+    out.push_str(&format!("  <id>{}/api/feed.atom</id>\n", escape_xml(site_url)));
+    out.push_str(&format!(
+        "  <link rel=\"alternate\" href=\"{}/\"/>\n",
+        escape_xml(site_url)
+    ));
+    out.push_str(&format!("  <updated>{}</updated>\n", escape_xml(&updated)));
+    out.push_str("  <author><name>NSW closest display</name></author>\n");
+
+    for entry in entries {
+        let name = entry
+            .location
+            .parse::<u32>()
+            .ok()
+            .and_then(|id| location_manager.get_by_id(id))
+            .map(|location| location.name)
+            .unwrap_or_else(|| format!("Location {}", entry.location));
+        let name = match entry.test_type {
+            TestType::Car => name,
+            test_type => format!("{} ({})", name, test_type.display()),
+        };
+        // Car entries keep the ids they had before other test types were tracked.
+        let entry_key = match entry.test_type {
+            TestType::Car => entry.location.clone(),
+            test_type => format!("{}-{}", entry.location, test_type.as_str()),
+        };
+        // The same slot can move earlier more than once, so each observation gets its own id.
+        let observed_at = DateTime::parse_from_rfc3339(&entry.scraped_at)
+            .map(|at| at.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string())
+            .unwrap_or_else(|_| entry.scraped_at.clone());
+
+        let summary = match &entry.previous {
+            Some(previous) => format!(
+                "Earliest available test at {} moved from {} to {}.",
+                name,
+                previous.display(),
+                entry.current.display()
+            ),
+            None => format!(
+                "{} has availability again, earliest test {}.",
+                name,
+                entry.current.display()
+            ),
+        };
+
+        out.push_str("  <entry>\n");
+        out.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_xml(&format!("{}: {}", name, entry.current.display()))
+        ));
+        out.push_str(&format!(
+            "    <id>{}/api/feed.atom#{}-{}-{}</id>\n",
+            escape_xml(site_url),
+            escape_xml(&entry_key),
+            entry.current.start_time.format("%Y%m%dT%H%M"),
+            escape_xml(&observed_at)
+        ));
+        out.push_str(&format!("    <link href=\"{}/\"/>\n", escape_xml(site_url)));
+        out.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            escape_xml(&entry.scraped_at)
+        ));
+        out.push_str(&format!("    <summary>{}</summary>\n", escape_xml(&summary)));
+        out.push_str("  </entry>\n");
+    }
+
+    out.push_str("</feed>\n");
+    out
+}
+
+/// `GET /api/feed.atom?locations=1,2,3&test_type=car`
+pub async fn atom_feed(Query(query): Query<FeedQuery>, site_url: String) -> Response {
+    let location_ids: Option<Vec<String>> = query.locations.as_deref().map(|ids| {
+        ids.split(',')
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect()
+    });
+
+    let test_type = match query.test_type.as_deref() {
+        Some(value) => match TestType::parse(value) {
+            Some(test_type) => Some(test_type),
+            None => return (StatusCode::BAD_REQUEST, "Unknown test type").into_response(),
+        },
+        None => None,
+    };
+    let entries = earlier_slot_entries(location_ids.as_deref(), test_type);
+
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        build_atom_feed(&site_url, &entries),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::shared_booking::parse_slot_time;
+
+    fn entry(scraped_at: &str, current: &str, previous: Option<&str>) -> EarlierSlotEntry {
+        let slot = |value: &str| TimeSlot {
+            availability: true,
+            slot_number: None,
+            start_time: parse_slot_time(value).unwrap(),
+            label: None,
+        };
+        EarlierSlotEntry {
+            location: "21".to_string(),
+            test_type: TestType::Car,
+            scraped_at: scraped_at.to_string(),
+            current: slot(current),
+            previous: previous.map(slot),
+        }
+    }
+
+    #[test]
+    fn feed_has_an_author_and_unique_entry_ids() {
+        // The slot was taken and then freed again by a second cancellation.
+        let entries = [
+            entry(
+                "2026-02-11T09:00:00+00:00",
+                "20/02/2026 9:05",
+                Some("03/03/2026 14:15"),
+            ),
+            entry("2026-02-10T09:00:00+00:00", "20/02/2026 9:05", None),
+        ];
+        let feed = build_atom_feed("https://example.com/", &entries);
+
+        assert!(feed.contains("<author><name>NSW closest display</name></author>"));
+        assert!(feed.contains(
+            "<id>https://example.com/api/feed.atom#21-20260220T0905-20260211T090000Z</id>"
+        ));
+        assert!(feed.contains(
+            "<id>https://example.com/api/feed.atom#21-20260220T0905-20260210T090000Z</id>"
+        ));
+    }
+}
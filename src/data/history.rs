@@ -0,0 +1,375 @@
+use axum::extract::{Path as UrlPath, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, Duration, Utc};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use super::json_lines::{JsonLinesLog, LogEntry};
+use super::shared_booking::{LocationBookings, TestType, TimeSlot};
+
+static HISTORY: OnceLock<Arc<RwLock<JsonLinesLog<HistoryEntry>>>> = OnceLock::new();
+
+fn get_history() -> &'static Arc<RwLock<JsonLinesLog<HistoryEntry>>> {
+    HISTORY.get_or_init(|| Arc::new(RwLock::new(JsonLinesLog::default())))
+}
+
+/// One scrape as it was recorded, only containing the locations scraped in that run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub scraped_at: String,
+    pub results: Vec<LocationBookings>,
+}
+
+impl HistoryEntry {
+    fn scraped_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.scraped_at)
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc))
+    }
+
+    fn location(&self, location_id: &str, test_type: TestType) -> Option<&LocationBookings> {
+        self.results
+            .iter()
+            .find(|loc| loc.is_for(location_id, test_type))
+    }
+}
+
+impl LogEntry for HistoryEntry {
+    const KIND: &'static str = "history entry";
+
+    fn recorded_at(&self) -> Option<DateTime<Utc>> {
+        self.scraped_at()
+    }
+}
+
+/// Append-only record of every scrape, stored as JSON lines next to `bookings.json`.
+pub struct HistoryManager;
+
+impl HistoryManager {
+    pub fn init_from_file(file_path: &str) -> Result<(), String> {
+        get_history()
+            .write()
+            .unwrap()
+            .init_from_file(file_path, Utc::now())
+    }
+
+    pub fn append(results: Vec<LocationBookings>, scraped_at: String) -> Result<(), String> {
+        let entry = HistoryEntry {
+            scraped_at,
+            results,
+        };
+        get_history().write().unwrap().append(entry, Utc::now())
+    }
+
+    /// Calls `f` with every entry scraped at or after `since`, oldest first. Entries older
+    /// than the in-memory window are streamed from the file.
+    pub fn for_each_since(since: DateTime<Utc>, f: impl FnMut(&HistoryEntry)) {
+        get_history().read().unwrap().for_each_since(since, f);
+    }
+
+    /// Slots for a location as they were last seen at or before `at`, with the scrape time.
+    pub fn slots_as_of(
+        location_id: &str,
+        test_type: TestType,
+        at: DateTime<Utc>,
+    ) -> Option<(String, Vec<TimeSlot>)> {
+        slots_as_of(&get_history().read().unwrap(), location_id, test_type, at)
+    }
+
+    /// Earliest available slot per scrape for a location, oldest scrape first.
+    pub fn earliest_slot_series(
+        location_id: &str,
+        test_type: TestType,
+        since: DateTime<Utc>,
+    ) -> Vec<(String, Option<TimeSlot>)> {
+        earliest_slot_series(
+            &get_history().read().unwrap(),
+            location_id,
+            test_type,
+            since,
+        )
+    }
+}
+
+fn slots_as_of(
+    history: &JsonLinesLog<HistoryEntry>,
+    location_id: &str,
+    test_type: TestType,
+    at: DateTime<Utc>,
+) -> Option<(String, Vec<TimeSlot>)> {
+    let in_memory = history
+        .entries()
+        .iter()
+        .rev()
+        .filter(|entry| entry.scraped_at().is_some_and(|scraped| scraped <= at))
+        .find_map(|entry| {
+            entry
+                .location(location_id, test_type)
+                .map(|loc| (entry.scraped_at.clone(), loc.slots.clone()))
+        });
+    if in_memory.is_some() || !history.has_file() {
+        return in_memory;
+    }
+
+    // Nothing recent enough in memory, so the answer is older than the window.
+    let mut found = None;
+    if let Err(e) = history.read_file(|entry| {
+        if entry.scraped_at().is_some_and(|scraped| scraped <= at) {
+            if let Some(loc) = entry.location(location_id, test_type) {
+                found = Some((entry.scraped_at.clone(), loc.slots.clone()));
+            }
+        }
+    }) {
+        error!("Failed to read booking history: {}", e);
+    }
+    found
+}
+
+fn earliest_slot_series(
+    history: &JsonLinesLog<HistoryEntry>,
+    location_id: &str,
+    test_type: TestType,
+    since: DateTime<Utc>,
+) -> Vec<(String, Option<TimeSlot>)> {
+    let mut series = Vec::new();
+    history.for_each_since(since, |entry| {
+        if let Some(loc) = entry.location(location_id, test_type) {
+            let earliest = loc
+                .slots
+                .iter()
+                .filter(|slot| slot.availability)
+                .min()
+                .cloned();
+            series.push((entry.scraped_at.clone(), earliest));
+        }
+    });
+    series
+}
+
+/// Earliest slot seen anywhere in `series`, with the scrape that saw it.
+fn earliest_in_series(series: &[(String, Option<TimeSlot>)]) -> Option<(String, TimeSlot)> {
+    series
+        .iter()
+        .filter_map(|(scraped_at, slot)| slot.as_ref().map(|slot| (scraped_at, slot)))
+        .min_by(|a, b| a.1.cmp(b.1))
+        .map(|(scraped_at, slot)| (scraped_at.clone(), slot.clone()))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryQuery {
+    /// Defaults to the car test.
+    pub test_type: Option<String>,
+    /// How many days back to look, 14 by default.
+    pub days: Option<i64>,
+    /// RFC 3339 time for `/slots`, now by default.
+    pub at: Option<String>,
+}
+
+impl HistoryQuery {
+    fn test_type(&self) -> Option<TestType> {
+        match self.test_type.as_deref() {
+            Some(value) => TestType::parse(value),
+            None => Some(TestType::Car),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EarliestSlotHistory {
+    earliest: Option<(String, TimeSlot)>,
+    series: Vec<(String, Option<TimeSlot>)>,
+}
+
+/// `GET /api/history/{location}?test_type=car&days=14`, the earliest slot per scrape.
+pub async fn location_history(
+    UrlPath(location_id): UrlPath<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    let Some(test_type) = query.test_type() else {
+        return (StatusCode::BAD_REQUEST, "Unknown test type").into_response();
+    };
+    let since = Utc::now() - Duration::days(query.days.unwrap_or(14).clamp(1, 365));
+
+    // Windows older than the in-memory history are read from the file.
+    let history = tokio::task::spawn_blocking(move || {
+        let series = HistoryManager::earliest_slot_series(&location_id, test_type, since);
+        EarliestSlotHistory {
+            earliest: earliest_in_series(&series),
+            series,
+        }
+    })
+    .await;
+
+    match history {
+        Ok(history) => Json(history).into_response(),
+        Err(e) => {
+            error!("Failed to read booking history: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SlotsAsOf {
+    scraped_at: String,
+    slots: Vec<TimeSlot>,
+}
+
+/// `GET /api/history/{location}/slots?at=2026-02-01T09:00:00Z`, the slots as they were then.
+pub async fn location_slots_as_of(
+    UrlPath(location_id): UrlPath<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    let Some(test_type) = query.test_type() else {
+        return (StatusCode::BAD_REQUEST, "Unknown test type").into_response();
+    };
+    let at = match query.at.as_deref() {
+        Some(value) => match DateTime::parse_from_rfc3339(value) {
+            Ok(at) => at.with_timezone(&Utc),
+            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid time").into_response(),
+        },
+        None => Utc::now(),
+    };
+
+    let slots = tokio::task::spawn_blocking(move || {
+        HistoryManager::slots_as_of(&location_id, test_type, at)
+    })
+    .await;
+
+    match slots {
+        Ok(Some((scraped_at, slots))) => Json(SlotsAsOf { scraped_at, slots }).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "No history for location").into_response(),
+        Err(e) => {
+            error!("Failed to read booking history: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::shared_booking::parse_slot_time;
+
+    /// Midnight UTC on the `n`th day of 2026.
+    fn day(n: i64) -> DateTime<Utc> {
+        "2026-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::days(n - 1)
+    }
+
+    fn slot(start_time: &str, availability: bool) -> TimeSlot {
+        TimeSlot {
+            availability,
+            slot_number: None,
+            start_time: parse_slot_time(start_time).unwrap(),
+            label: None,
+        }
+    }
+
+    fn entry(scraped_at: DateTime<Utc>, locations: &[(&str, Vec<TimeSlot>)]) -> HistoryEntry {
+        HistoryEntry {
+            scraped_at: scraped_at.to_rfc3339(),
+            results: locations
+                .iter()
+                .map(|(location, slots)| LocationBookings {
+                    location: location.to_string(),
+                    test_type: TestType::Car,
+                    slots: slots.clone(),
+                    next_available_date: None,
+                    last_scraped: None,
+                    stale: false,
+                })
+                .collect(),
+        }
+    }
+
+    fn history_file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("history-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn slots_as_of_uses_the_last_scrape_of_the_location() {
+        let mut history = JsonLinesLog::default();
+        let first = vec![slot("10/03/2026 9:05", true)];
+        let second = vec![slot("02/03/2026 14:15", true)];
+        history
+            .append(entry(day(1), &[("21", first.clone())]), day(1))
+            .unwrap();
+        history
+            .append(entry(day(2), &[("21", second.clone())]), day(2))
+            .unwrap();
+        // A later scrape that didn't cover 21 doesn't hide its slots.
+        history
+            .append(entry(day(3), &[("68", Vec::new())]), day(3))
+            .unwrap();
+
+        let as_of = |at| slots_as_of(&history, "21", TestType::Car, at);
+        assert_eq!(as_of(day(1)), Some((day(1).to_rfc3339(), first)));
+        assert_eq!(as_of(day(3)), Some((day(2).to_rfc3339(), second)));
+        assert_eq!(as_of(day(1) - Duration::hours(1)), None);
+        assert_eq!(slots_as_of(&history, "21", TestType::Rider, day(3)), None);
+    }
+
+    #[test]
+    fn only_the_window_stays_in_memory() {
+        let mut history = JsonLinesLog::default();
+        history
+            .append(entry(day(1), &[("21", Vec::new())]), day(1))
+            .unwrap();
+        history
+            .append(entry(day(40), &[("21", Vec::new())]), day(40))
+            .unwrap();
+
+        let scraped: Vec<_> = history.entries().iter().map(|e| e.scraped_at()).collect();
+        assert_eq!(scraped, vec![Some(day(40))]);
+        // Without a file the pruned scrape is gone.
+        assert_eq!(slots_as_of(&history, "21", TestType::Car, day(2)), None);
+    }
+
+    #[test]
+    fn reads_the_file_for_scrapes_older_than_the_window() {
+        let file_path = history_file("fallback");
+        let mut history = JsonLinesLog::default();
+        history.init_from_file(&file_path, day(1)).unwrap();
+        let old = vec![
+            slot("05/02/2026 9:05", true),
+            slot("01/02/2026 8:00", false),
+        ];
+        let recent = vec![slot("20/02/2026 10:50", true)];
+        history
+            .append(entry(day(1), &[("21", old.clone())]), day(1))
+            .unwrap();
+        history
+            .append(entry(day(40), &[("21", recent)]), day(40))
+            .unwrap();
+        assert_eq!(history.entries().len(), 1);
+
+        assert_eq!(
+            slots_as_of(&history, "21", TestType::Car, day(5)),
+            Some((day(1).to_rfc3339(), old))
+        );
+
+        let series = earliest_slot_series(&history, "21", TestType::Car, day(1));
+        assert_eq!(
+            series,
+            vec![
+                (day(1).to_rfc3339(), Some(slot("05/02/2026 9:05", true))),
+                (day(40).to_rfc3339(), Some(slot("20/02/2026 10:50", true))),
+            ]
+        );
+        assert_eq!(
+            earliest_in_series(&series),
+            Some((day(1).to_rfc3339(), slot("05/02/2026 9:05", true)))
+        );
+        assert_eq!(
+            earliest_slot_series(&history, "21", TestType::Car, day(20)).len(),
+            1
+        );
+
+        let _ = std::fs::remove_file(&file_path);
+    }
+}
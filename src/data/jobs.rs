@@ -0,0 +1,609 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use super::scraper::{ScrapeError, ScrapeFuture, ScrapeOutput, Scraper};
+use super::shared_booking::TestType;
+use crate::settings::Settings;
+
+static JOB_QUEUE: OnceLock<Arc<RwLock<JobQueueState>>> = OnceLock::new();
+
+fn get_job_queue() -> &'static Arc<RwLock<JobQueueState>> {
+    JOB_QUEUE.get_or_init(|| Arc::new(RwLock::new(JobQueueState::default())))
+}
+
+/// A batch of locations for a worker to scrape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrapeJob {
+    pub id: u64,
+    pub test_type: TestType,
+    pub locations: Vec<String>,
+    /// How long the worker holds the job without renewing the lease.
+    pub lease_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Lease {
+    worker: String,
+    expires_at: DateTime<Utc>,
+}
+
+struct QueuedJob {
+    job: ScrapeJob,
+    lease: Option<Lease>,
+    result: Option<ScrapeOutput>,
+}
+
+#[derive(Default)]
+struct JobQueueState {
+    next_id: u64,
+    jobs: BTreeMap<u64, QueuedJob>,
+    /// When each worker last leased, renewed or completed a job.
+    workers: HashMap<String, DateTime<Utc>>,
+}
+
+/// Why a job's result was turned away.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// The job is finished or cancelled.
+    Gone,
+    /// The job was never leased to this worker, or has been leased to another one since.
+    NotLeased,
+}
+
+impl JobQueueState {
+    fn enqueue(
+        &mut self,
+        test_type: TestType,
+        locations: Vec<String>,
+        batch_size: usize,
+        lease_secs: u64,
+    ) -> Vec<u64> {
+        let mut ids = Vec::new();
+
+        for batch in locations.chunks(batch_size.max(1)) {
+            self.next_id += 1;
+            let id = self.next_id;
+            self.jobs.insert(
+                id,
+                QueuedJob {
+                    job: ScrapeJob {
+                        id,
+                        test_type,
+                        locations: batch.to_vec(),
+                        lease_secs,
+                    },
+                    lease: None,
+                    result: None,
+                },
+            );
+            ids.push(id);
+        }
+
+        ids
+    }
+
+    fn lease(&mut self, worker: &str, now: DateTime<Utc>) -> Option<ScrapeJob> {
+        self.workers.insert(worker.to_string(), now);
+
+        let queued = self.jobs.values_mut().find(|queued| {
+            queued.result.is_none()
+                && queued
+                    .lease
+                    .as_ref()
+                    .is_none_or(|lease| lease.expires_at <= now)
+        })?;
+
+        if let Some(expired) = &queued.lease {
+            warn!(
+                "Lease on job {} held by {} expired, reassigning it to {}",
+                queued.job.id, expired.worker, worker
+            );
+        }
+
+        queued.lease = Some(Lease {
+            worker: worker.to_string(),
+            expires_at: now + Duration::seconds(queued.job.lease_secs as i64),
+        });
+        Some(queued.job.clone())
+    }
+
+    fn renew(&mut self, id: u64, worker: &str, now: DateTime<Utc>) -> bool {
+        self.workers.insert(worker.to_string(), now);
+
+        let Some(queued) = self.jobs.get_mut(&id) else {
+            return false;
+        };
+        match &mut queued.lease {
+            Some(lease) if lease.worker == worker && queued.result.is_none() => {
+                lease.expires_at = now + Duration::seconds(queued.job.lease_secs as i64);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn release(&mut self, id: u64, worker: &str, now: DateTime<Utc>) -> bool {
+        self.workers.insert(worker.to_string(), now);
+
+        let Some(queued) = self.jobs.get_mut(&id) else {
+            return false;
+        };
+        if queued.result.is_some() || queued.lease.as_ref().is_none_or(|l| l.worker != worker) {
+            return false;
+        }
+
+        info!("Worker {} released job {}", worker, id);
+        queued.lease = None;
+        true
+    }
+
+    fn complete(
+        &mut self,
+        id: u64,
+        worker: &str,
+        output: ScrapeOutput,
+        now: DateTime<Utc>,
+    ) -> Result<(), Rejection> {
+        self.workers.insert(worker.to_string(), now);
+
+        let queued = self.jobs.get_mut(&id).ok_or(Rejection::Gone)?;
+        if queued.result.is_some() {
+            return Err(Rejection::Gone);
+        }
+        // An expired lease still belongs to its worker until someone else leases the job.
+        if queued
+            .lease
+            .as_ref()
+            .is_none_or(|lease| lease.worker != worker)
+        {
+            warn!(
+                "Ignoring result for job {} from {}, which doesn't hold it",
+                id, worker
+            );
+            return Err(Rejection::NotLeased);
+        }
+
+        let output = accept_output(&queued.job, worker, output);
+        info!(
+            "Worker {} finished job {}: {} of {} locations scraped",
+            worker,
+            id,
+            output.bookings.len(),
+            queued.job.locations.len()
+        );
+        queued.result = Some(output);
+        Ok(())
+    }
+
+    fn is_done(&self, ids: &[u64]) -> bool {
+        ids.iter().all(|id| {
+            self.jobs
+                .get(id)
+                .is_none_or(|queued| queued.result.is_some())
+        })
+    }
+
+    fn take_results(&mut self, ids: &[u64]) -> Vec<ScrapeOutput> {
+        ids.iter()
+            .filter_map(|id| self.jobs.remove(id))
+            .filter_map(|queued| queued.result)
+            .collect()
+    }
+}
+
+/// Keeps only what a worker reported about the job's own locations, and parses the bookings
+/// from the raw payloads here rather than trusting the worker's parser.
+fn accept_output(job: &ScrapeJob, worker: &str, output: ScrapeOutput) -> ScrapeOutput {
+    let in_job = |location: &String| job.locations.contains(location);
+
+    let foreign = output
+        .bookings
+        .keys()
+        .chain(output.failures.keys())
+        .chain(output.payloads.keys())
+        .filter(|location| !in_job(location))
+        .count();
+    if foreign > 0 {
+        warn!(
+            "Dropping {} results from {} for locations outside job {}",
+            foreign, worker, job.id
+        );
+    }
+    let unparsed = output
+        .bookings
+        .keys()
+        .filter(|location| in_job(location) && !output.payloads.contains_key(*location))
+        .count();
+    if unparsed > 0 {
+        warn!(
+            "Dropping {} bookings from {} that came without a timeslots payload",
+            unparsed, worker
+        );
+    }
+
+    let mut accepted = ScrapeOutput {
+        failures: output
+            .failures
+            .into_iter()
+            .filter(|(location, _)| in_job(location))
+            .collect(),
+        durations_ms: output
+            .durations_ms
+            .into_iter()
+            .filter(|(location, _)| in_job(location))
+            .collect(),
+        ..Default::default()
+    };
+    for mut blocked in output.blocked_proxies {
+        blocked.locations.retain(|location| in_job(location));
+        accepted.add_blocked(blocked);
+    }
+    for (location, payload) in output.payloads {
+        if in_job(&location) {
+            accepted.add_payload(&location, job.test_type, payload);
+        }
+    }
+    accepted
+}
+
+/// Location-scrape jobs the `workers` backend hands out over HTTP. A job whose lease runs
+/// out before its result arrives goes to the next worker that asks.
+pub struct JobQueue;
+
+impl JobQueue {
+    /// Splits `locations` into jobs of up to `batch_size` and returns their ids.
+    pub fn enqueue(
+        test_type: TestType,
+        locations: Vec<String>,
+        batch_size: usize,
+        lease_secs: u64,
+    ) -> Vec<u64> {
+        get_job_queue()
+            .write()
+            .unwrap()
+            .enqueue(test_type, locations, batch_size, lease_secs)
+    }
+
+    /// Leases the oldest job that has no result and no live lease.
+    pub fn lease(worker: &str, now: DateTime<Utc>) -> Option<ScrapeJob> {
+        get_job_queue().write().unwrap().lease(worker, now)
+    }
+
+    /// Extends the lease on a job. Fails if the job is gone or another worker holds it now.
+    pub fn renew(id: u64, worker: &str, now: DateTime<Utc>) -> bool {
+        get_job_queue().write().unwrap().renew(id, worker, now)
+    }
+
+    /// Hands a job back for another worker to lease right away.
+    pub fn release(id: u64, worker: &str, now: DateTime<Utc>) -> bool {
+        get_job_queue().write().unwrap().release(id, worker, now)
+    }
+
+    /// Stores a job's result. Only the worker that leased the job last may post it, which
+    /// may be after its lease expired as long as nobody else has leased the job since.
+    pub fn complete(
+        id: u64,
+        worker: &str,
+        output: ScrapeOutput,
+        now: DateTime<Utc>,
+    ) -> Result<(), Rejection> {
+        get_job_queue()
+            .write()
+            .unwrap()
+            .complete(id, worker, output, now)
+    }
+
+    fn is_done(ids: &[u64]) -> bool {
+        get_job_queue().read().unwrap().is_done(ids)
+    }
+
+    /// Removes the jobs from the queue and returns the results that arrived.
+    fn take_results(ids: &[u64]) -> Vec<ScrapeOutput> {
+        get_job_queue().write().unwrap().take_results(ids)
+    }
+}
+
+/// Hands the locations of a scrape to workers through the `JobQueue` and waits for their
+/// results, for at most `scrape_timeout_min`.
+pub struct WorkerQueueScraper;
+
+impl Scraper for WorkerQueueScraper {
+    fn name(&self) -> &'static str {
+        "workers"
+    }
+
+    // Workers bring their own.
+    fn requires_proxies(&self) -> bool {
+        false
+    }
+
+    fn scrape<'a>(
+        &'a self,
+        locations: Vec<String>,
+        test_type: TestType,
+        settings: &'a Settings,
+        _proxies: Vec<String>,
+        _artifact_dir: Option<String>,
+    ) -> ScrapeFuture<'a> {
+        Box::pin(async move {
+            let total = locations.len();
+            let ids = JobQueue::enqueue(
+                test_type,
+                locations,
+                settings.worker_batch_size,
+                settings.job_lease_secs,
+            );
+            info!(
+                "Queued {} {} jobs for {} locations",
+                ids.len(),
+                test_type.as_str(),
+                total
+            );
+
+            let deadline = tokio::time::Instant::now()
+                + std::time::Duration::from_secs(settings.scrape_timeout_min * 60);
+            while !JobQueue::is_done(&ids) && tokio::time::Instant::now() < deadline {
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+
+            let results = JobQueue::take_results(&ids);
+            if results.is_empty() {
+                return Err(ScrapeError(format!(
+                    "no worker finished any of {} jobs within {} minutes",
+                    ids.len(),
+                    settings.scrape_timeout_min
+                )));
+            }
+            if results.len() < ids.len() {
+                warn!(
+                    "{} of {} jobs had no result by the deadline",
+                    ids.len() - results.len(),
+                    ids.len()
+                );
+            }
+
+            let mut output = ScrapeOutput::default();
+            for result in results {
+                output.merge(result);
+            }
+
+            info!(
+                "Scraping complete: {} locations scraped, {} failed.",
+                output.bookings.len(),
+                output.failures.len()
+            );
+            Ok(output)
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WorkerRequest {
+    pub worker: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JobResult {
+    pub worker: String,
+    pub output: ScrapeOutput,
+}
+
+/// `POST /api/worker/lease`, answered with a job or `204 No Content` when there is none.
+pub async fn lease_job(Json(request): Json<WorkerRequest>) -> Response {
+    match JobQueue::lease(&request.worker, Utc::now()) {
+        Some(job) => Json(job).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// `POST /api/worker/jobs/{id}/renew`, `409 Conflict` once the lease is lost.
+pub async fn renew_job(Path(id): Path<u64>, Json(request): Json<WorkerRequest>) -> Response {
+    if JobQueue::renew(id, &request.worker, Utc::now()) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        StatusCode::CONFLICT.into_response()
+    }
+}
+
+/// `POST /api/worker/jobs/{id}/release`, for a worker that can't scrape the job it leased.
+pub async fn release_job(Path(id): Path<u64>, Json(request): Json<WorkerRequest>) -> Response {
+    if JobQueue::release(id, &request.worker, Utc::now()) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        StatusCode::CONFLICT.into_response()
+    }
+}
+
+/// `POST /api/worker/jobs/{id}/result`, `410 Gone` when the job is finished or cancelled and
+/// `409 Conflict` when it was leased to another worker.
+pub async fn complete_job(Path(id): Path<u64>, Json(result): Json<JobResult>) -> Response {
+    match JobQueue::complete(id, &result.worker, result.output, Utc::now()) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(Rejection::Gone) => StatusCode::GONE.into_response(),
+        Err(Rejection::NotLeased) => StatusCode::CONFLICT.into_response(),
+    }
+}
+
+#[derive(Serialize)]
+struct JobStatus {
+    id: u64,
+    test_type: TestType,
+    locations: usize,
+    lease: Option<Lease>,
+    done: bool,
+}
+
+#[derive(Serialize)]
+struct WorkerStatus {
+    jobs: Vec<JobStatus>,
+    workers: BTreeMap<String, DateTime<Utc>>,
+}
+
+/// `GET /api/status/workers`
+pub async fn worker_status() -> Response {
+    let queue = get_job_queue().read().unwrap();
+    Json(WorkerStatus {
+        jobs: queue
+            .jobs
+            .values()
+            .map(|queued| JobStatus {
+                id: queued.job.id,
+                test_type: queued.job.test_type,
+                locations: queued.job.locations.len(),
+                lease: queued.lease.clone(),
+                done: queued.result.is_some(),
+            })
+            .collect(),
+        workers: queue
+            .workers
+            .iter()
+            .map(|(worker, last_seen)| (worker.clone(), *last_seen))
+            .collect(),
+    })
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::scraper::{BlockedProxy, FailureCategory, LocationFailure};
+
+    const PAYLOAD: &str = r#"{"ajaxresult":{"slots":{"listTimeSlot":[{"availability":true,"startTime":"11/03/2026 14:15"}]}}}"#;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_770_000_000 + secs, 0).unwrap()
+    }
+
+    fn queue_with_job(locations: &[&str]) -> (JobQueueState, u64) {
+        let mut queue = JobQueueState::default();
+        let ids = queue.enqueue(
+            TestType::Car,
+            locations
+                .iter()
+                .map(|location| location.to_string())
+                .collect(),
+            10,
+            60,
+        );
+        (queue, ids[0])
+    }
+
+    fn payload_output(location: &str) -> ScrapeOutput {
+        let mut output = ScrapeOutput::default();
+        output.add_payload(location, TestType::Car, PAYLOAD.to_string());
+        output
+    }
+
+    #[test]
+    fn expired_lease_goes_to_the_next_worker() {
+        let (mut queue, id) = queue_with_job(&["21"]);
+
+        assert_eq!(queue.lease("a", at(0)).map(|job| job.id), Some(id));
+        assert!(queue.lease("b", at(30)).is_none());
+        assert!(queue.renew(id, "a", at(30)));
+
+        // Renewed at 30 for 60 seconds.
+        assert!(queue.lease("b", at(60)).is_none());
+        assert_eq!(queue.lease("b", at(90)).map(|job| job.id), Some(id));
+        assert!(!queue.renew(id, "a", at(91)));
+        assert!(queue.renew(id, "b", at(91)));
+    }
+
+    #[test]
+    fn only_the_holder_renews_or_releases() {
+        let (mut queue, id) = queue_with_job(&["21"]);
+        queue.lease("a", at(0)).unwrap();
+
+        assert!(!queue.renew(id, "b", at(10)));
+        assert!(!queue.renew(id + 1, "a", at(10)));
+        assert!(!queue.release(id, "b", at(10)));
+
+        assert!(queue.release(id, "a", at(10)));
+        assert_eq!(queue.lease("b", at(11)).map(|job| job.id), Some(id));
+    }
+
+    #[test]
+    fn result_after_reassignment_is_rejected() {
+        let (mut queue, id) = queue_with_job(&["21"]);
+
+        assert_eq!(
+            queue.complete(id, "a", payload_output("21"), at(0)),
+            Err(Rejection::NotLeased)
+        );
+
+        queue.lease("a", at(0)).unwrap();
+        queue.lease("b", at(120)).unwrap();
+        assert_eq!(
+            queue.complete(id, "a", payload_output("21"), at(121)),
+            Err(Rejection::NotLeased)
+        );
+        assert!(!queue.is_done(&[id]));
+
+        assert_eq!(
+            queue.complete(id, "b", payload_output("21"), at(122)),
+            Ok(())
+        );
+        assert!(queue.is_done(&[id]));
+        assert_eq!(
+            queue.complete(id, "b", payload_output("21"), at(123)),
+            Err(Rejection::Gone)
+        );
+        assert_eq!(queue.take_results(&[id]).len(), 1);
+    }
+
+    #[test]
+    fn expired_lease_still_completes_until_reassigned() {
+        let (mut queue, id) = queue_with_job(&["21"]);
+        queue.lease("a", at(0)).unwrap();
+
+        assert_eq!(
+            queue.complete(id, "a", payload_output("21"), at(600)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn results_are_limited_to_the_job_and_parsed_from_payloads() {
+        let (mut queue, id) = queue_with_job(&["21", "68", "96"]);
+        queue.lease("a", at(0)).unwrap();
+
+        let mut output = payload_output("21");
+        // Bookings for a location outside the job, and bookings without a payload.
+        output.add_payload("99", TestType::Car, PAYLOAD.to_string());
+        let mut unbacked = output.bookings["21"].clone();
+        unbacked.location = "68".to_string();
+        output.bookings.insert("68".to_string(), unbacked);
+        output.bookings.get_mut("21").unwrap().slots.clear();
+        output.failures.insert(
+            "96".to_string(),
+            LocationFailure {
+                category: FailureCategory::ElementTimeout,
+                message: "timeout".to_string(),
+                duration_ms: None,
+                artifacts: None,
+            },
+        );
+        output.add_blocked(BlockedProxy {
+            proxy: "http://proxy:8080".to_string(),
+            status_code: 403,
+            response_body: String::new(),
+            locations: vec!["68".to_string(), "99".to_string()],
+        });
+
+        queue.complete(id, "a", output, at(10)).unwrap();
+        let result = queue.take_results(&[id]).remove(0);
+
+        assert_eq!(result.bookings.len(), 1);
+        assert_eq!(result.bookings["21"].slots.len(), 1);
+        assert_eq!(result.payloads.len(), 1);
+        assert!(result.failures.contains_key("96"));
+        assert_eq!(result.blocked_proxies[0].locations, vec!["68".to_string()]);
+    }
+}
@@ -0,0 +1,237 @@
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, error};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Entries newer than this stay in memory, older ones are read from the file when asked for.
+const MEMORY_WINDOW_DAYS: i64 = 30;
+
+/// Something recorded in a [`JsonLinesLog`].
+pub trait LogEntry: Serialize + DeserializeOwned {
+    /// Names the entries in log messages and errors.
+    const KIND: &'static str;
+
+    /// When the entry was recorded. Entries without a valid time are never kept in memory.
+    fn recorded_at(&self) -> Option<DateTime<Utc>>;
+}
+
+/// Append-only log stored as JSON lines. Only the last `MEMORY_WINDOW_DAYS` are kept in
+/// memory, older entries are streamed from the file.
+pub struct JsonLinesLog<T> {
+    file_path: Option<String>,
+    /// Every entry recorded at or after `memory_start`, oldest first.
+    entries: Vec<T>,
+    memory_start: Option<DateTime<Utc>>,
+}
+
+impl<T> Default for JsonLinesLog<T> {
+    fn default() -> Self {
+        Self {
+            file_path: None,
+            entries: Vec::new(),
+            memory_start: None,
+        }
+    }
+}
+
+impl<T: LogEntry> JsonLinesLog<T> {
+    /// Backs the log with `file_path` and loads the entries in the window from it.
+    pub fn init_from_file(&mut self, file_path: &str, now: DateTime<Utc>) -> Result<(), String> {
+        self.file_path = Some(file_path.to_string());
+        self.prune(now);
+
+        if !Path::new(file_path).exists() {
+            debug!("No path for {} log", T::KIND);
+            return Ok(());
+        }
+
+        let start = self.memory_start;
+        let mut entries = Vec::new();
+        self.read_file(|entry| {
+            if entry
+                .recorded_at()
+                .is_some_and(|at| start.is_none_or(|start| at >= start))
+            {
+                entries.push(entry);
+            }
+        })?;
+        self.entries = entries;
+
+        Ok(())
+    }
+
+    pub fn append(&mut self, entry: T, now: DateTime<Utc>) -> Result<(), String> {
+        if let Some(file_path) = &self.file_path {
+            let line = serde_json::to_string(&entry)
+                .map_err(|e| format!("Failed to serialize {}: {}", T::KIND, e))?;
+
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file_path)
+                .and_then(|mut file| writeln!(file, "{}", line))
+                .map_err(|e| format!("Failed to append to file: {}", e))?;
+        }
+
+        self.entries.push(entry);
+        self.prune(now);
+        Ok(())
+    }
+
+    /// Entries in the in-memory window, oldest first.
+    pub fn entries(&self) -> &[T] {
+        &self.entries
+    }
+
+    pub fn has_file(&self) -> bool {
+        self.file_path.is_some()
+    }
+
+    /// Whether every entry since `since` is in memory.
+    pub fn in_memory(&self, since: DateTime<Utc>) -> bool {
+        self.file_path.is_none() || self.memory_start.is_none_or(|start| since >= start)
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let start = now - Duration::days(MEMORY_WINDOW_DAYS);
+        self.entries
+            .retain(|entry| entry.recorded_at().is_some_and(|at| at >= start));
+        self.memory_start = Some(start);
+    }
+
+    /// Calls `f` with every entry recorded at or after `since`, oldest first, reading the
+    /// file for entries older than the in-memory window.
+    pub fn for_each_since(&self, since: DateTime<Utc>, mut f: impl FnMut(&T)) {
+        let in_range = |entry: &T| entry.recorded_at().is_some_and(|at| at >= since);
+
+        if self.in_memory(since) {
+            self.entries.iter().filter(|e| in_range(e)).for_each(f);
+            return;
+        }
+
+        if let Err(e) = self.read_file(|entry| {
+            if in_range(&entry) {
+                f(&entry);
+            }
+        }) {
+            error!("Failed to read {} log: {}", T::KIND, e);
+        }
+    }
+
+    /// Reads the whole file line by line, skipping malformed entries.
+    pub fn read_file(&self, mut f: impl FnMut(T)) -> Result<(), String> {
+        let Some(file_path) = &self.file_path else {
+            return Ok(());
+        };
+        if !Path::new(file_path).exists() {
+            return Ok(());
+        }
+
+        let file = File::open(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+        for (idx, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("Failed to read file: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<T>(&line) {
+                Ok(entry) => f(entry),
+                Err(e) => error!("Skipping malformed {} on line {}: {}", T::KIND, idx + 1, e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Entry {
+        at: DateTime<Utc>,
+    }
+
+    impl LogEntry for Entry {
+        const KIND: &'static str = "test entry";
+
+        fn recorded_at(&self) -> Option<DateTime<Utc>> {
+            Some(self.at)
+        }
+    }
+
+    /// Midnight on the `n`th day of 2026.
+    fn day(n: i64) -> DateTime<Utc> {
+        "2026-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::days(n - 1)
+    }
+
+    fn log_file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("json-lines-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn collect_since(log: &JsonLinesLog<Entry>, since: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let mut seen = Vec::new();
+        log.for_each_since(since, |entry| seen.push(entry.at));
+        seen
+    }
+
+    #[test]
+    fn keeps_only_the_window_in_memory() {
+        let mut log = JsonLinesLog::default();
+        log.append(Entry { at: day(1) }, day(1)).unwrap();
+        log.append(Entry { at: day(20) }, day(20)).unwrap();
+        log.append(Entry { at: day(36) }, day(36)).unwrap();
+
+        let at: Vec<_> = log.entries().iter().map(|entry| entry.at).collect();
+        assert_eq!(at, vec![day(20), day(36)]);
+        // Without a file, what was pruned is gone.
+        assert!(log.in_memory(day(1)));
+        assert_eq!(collect_since(&log, day(1)), vec![day(20), day(36)]);
+    }
+
+    #[test]
+    fn reads_the_file_for_entries_older_than_the_window() {
+        let file_path = log_file("fallback");
+        let mut log = JsonLinesLog::default();
+        log.init_from_file(&file_path, day(1)).unwrap();
+        log.append(Entry { at: day(1) }, day(1)).unwrap();
+        log.append(Entry { at: day(20) }, day(20)).unwrap();
+        log.append(Entry { at: day(36) }, day(36)).unwrap();
+
+        assert_eq!(log.entries().len(), 2);
+        assert!(log.in_memory(day(20)));
+        assert!(!log.in_memory(day(1)));
+        assert_eq!(collect_since(&log, day(1)), vec![day(1), day(20), day(36)]);
+        assert_eq!(collect_since(&log, day(20)), vec![day(20), day(36)]);
+
+        let _ = std::fs::remove_file(&file_path);
+    }
+
+    #[test]
+    fn loads_the_window_and_skips_malformed_lines() {
+        let file_path = log_file("load");
+        let lines = [
+            serde_json::to_string(&Entry { at: day(1) }).unwrap(),
+            "not json".to_string(),
+            String::new(),
+            serde_json::to_string(&Entry { at: day(25) }).unwrap(),
+        ];
+        std::fs::write(&file_path, lines.join("\n")).unwrap();
+
+        let mut log = JsonLinesLog::<Entry>::default();
+        log.init_from_file(&file_path, day(36)).unwrap();
+        assert_eq!(log.entries(), &[Entry { at: day(25) }]);
+
+        let mut all = Vec::new();
+        log.read_file(|entry| all.push(entry.at)).unwrap();
+        assert_eq!(all, vec![day(1), day(25)]);
+
+        let _ = std::fs::remove_file(&file_path);
+    }
+}
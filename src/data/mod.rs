@@ -1,9 +1,55 @@
 pub mod location;
 pub mod shared_booking;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod artifacts;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod auth;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod booking;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod calendar;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod circuit_breaker;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod demo;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod diff;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod discord;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod feed;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod history;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod jobs;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod json_lines;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod payloads;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod proxy_pool;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod proxy_source;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod replay;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod rta;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod runs;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod schedule;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod scraper;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod status;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod store;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod subprocess;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod timeslots;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod worker;
@@ -0,0 +1,339 @@
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::history::HistoryEntry;
+use super::shared_booking::TestType;
+use super::store::temp_path;
+use super::timeslots::parse_timeslots;
+
+/// A raw `timeslots` payload as RTA sent it, kept so history can be rebuilt with a newer
+/// parser.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchivedPayload {
+    pub run_id: String,
+    pub scraped_at: String,
+    pub location: String,
+    pub test_type: TestType,
+    pub payload: String,
+}
+
+impl ArchivedPayload {
+    fn scraped_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.scraped_at)
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc))
+    }
+}
+
+/// Whether an archive line is older than `cutoff`. Lines that can't be read are kept for
+/// `reprocess_payloads` to report.
+fn is_expired(line: &str, cutoff: DateTime<Utc>) -> bool {
+    serde_json::from_str::<ArchivedPayload>(line)
+        .ok()
+        .and_then(|archived| archived.scraped_at())
+        .is_some_and(|scraped_at| scraped_at < cutoff)
+}
+
+/// Appends the payloads of one scrape attempt to the JSON lines archive at `path`.
+pub fn archive_payloads(
+    path: &str,
+    run_id: &str,
+    test_type: TestType,
+    payloads: &HashMap<String, String>,
+) -> Result<(), String> {
+    let scraped_at = Utc::now().to_rfc3339();
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+
+    for (location, payload) in payloads {
+        let line = serde_json::to_string(&ArchivedPayload {
+            run_id: run_id.to_string(),
+            scraped_at: scraped_at.clone(),
+            location: location.clone(),
+            test_type,
+            payload: payload.clone(),
+        })
+        .map_err(|e| format!("Failed to serialize payload: {}", e))?;
+
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to append to file: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Drops payloads scraped more than `retention_days` before `now` from the archive at
+/// `path`. Payloads are appended in order, so the file is only rewritten when its first line
+/// has expired.
+pub fn prune_archive(path: &str, retention_days: u64, now: DateTime<Utc>) -> Result<(), String> {
+    let cutoff = now - Duration::days(retention_days as i64);
+
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Failed to open '{}': {}", path, e)),
+    };
+    let mut lines = BufReader::new(file).lines();
+
+    match lines.next() {
+        Some(Ok(first)) if is_expired(&first, cutoff) => {}
+        Some(Err(e)) => return Err(format!("Failed to read '{}': {}", path, e)),
+        _ => {
+            debug!("No expired payloads in '{}'", path);
+            return Ok(());
+        }
+    }
+
+    let tmp_path = temp_path(Path::new(path));
+    let mut output = BufWriter::new(
+        File::create(&tmp_path)
+            .map_err(|e| format!("Failed to create '{}': {}", tmp_path.display(), e))?,
+    );
+    let mut removed = 1;
+    for line in lines {
+        let line = line.map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+        if is_expired(&line, cutoff) {
+            removed += 1;
+        } else {
+            writeln!(output, "{}", line).map_err(|e| format!("Failed to write file: {}", e))?;
+        }
+    }
+    output
+        .flush()
+        .map_err(|e| format!("Failed to write '{}': {}", tmp_path.display(), e))?;
+    drop(output);
+
+    fs::rename(&tmp_path, Path::new(path))
+        .map_err(|e| format!("Failed to replace '{}': {}", path, e))?;
+
+    info!(
+        "Removed {} payloads older than {} days from '{}'",
+        removed, retention_days, path
+    );
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct ReprocessSummary {
+    pub runs: usize,
+    pub payloads: usize,
+    pub invalid: usize,
+    pub unknown_fields: BTreeSet<String>,
+}
+
+/// Collects the payloads of the run being read into one history entry.
+#[derive(Default)]
+struct RunPayloads {
+    run_id: String,
+    scraped_at: String,
+    /// Later attempts of the run replace earlier payloads for the same location.
+    payloads: BTreeMap<(String, TestType), String>,
+}
+
+impl RunPayloads {
+    fn write_entry(
+        &mut self,
+        output: &mut impl Write,
+        summary: &mut ReprocessSummary,
+    ) -> Result<(), String> {
+        if self.payloads.is_empty() {
+            return Ok(());
+        }
+
+        let mut results = Vec::new();
+        for ((location, test_type), payload) in std::mem::take(&mut self.payloads) {
+            match parse_timeslots(&payload) {
+                Ok(timeslots) => {
+                    summary.unknown_fields.extend(timeslots.unknown_fields());
+                    let mut bookings = timeslots.into_bookings(&location, test_type);
+                    // History only keeps available slots.
+                    bookings.slots.retain(|slot| slot.availability);
+                    results.push(bookings);
+                }
+                Err(e) => {
+                    error!(
+                        "Skipping payload for location {} in run {}: {}",
+                        location, self.run_id, e
+                    );
+                    summary.invalid += 1;
+                }
+            }
+        }
+
+        let line = serde_json::to_string(&HistoryEntry {
+            scraped_at: self.scraped_at.clone(),
+            results,
+        })
+        .map_err(|e| format!("Failed to serialize history entry: {}", e))?;
+        writeln!(output, "{}", line).map_err(|e| format!("Failed to write file: {}", e))?;
+
+        summary.runs += 1;
+        Ok(())
+    }
+}
+
+/// Parses every payload in the archive at `archive_path` again and writes the result as a
+/// history file to `output_path`, one entry per run.
+pub fn reprocess_payloads(
+    archive_path: &str,
+    output_path: &str,
+) -> Result<ReprocessSummary, String> {
+    let archive = File::open(archive_path)
+        .map_err(|e| format!("Failed to open '{}': {}", archive_path, e))?;
+    let mut output = BufWriter::new(
+        File::create(output_path)
+            .map_err(|e| format!("Failed to create '{}': {}", output_path, e))?,
+    );
+
+    let mut summary = ReprocessSummary::default();
+    let mut run = RunPayloads::default();
+
+    for (idx, line) in BufReader::new(archive).lines().enumerate() {
+        let line = line.map_err(|e| format!("Failed to read '{}': {}", archive_path, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let archived = match serde_json::from_str::<ArchivedPayload>(&line) {
+            Ok(archived) => archived,
+            Err(e) => {
+                error!(
+                    "Skipping malformed archived payload on line {}: {}",
+                    idx + 1,
+                    e
+                );
+                summary.invalid += 1;
+                continue;
+            }
+        };
+
+        if archived.run_id != run.run_id {
+            run.write_entry(&mut output, &mut summary)?;
+            run.run_id = archived.run_id;
+        }
+        // A run is dated by its first attempt, not whichever attempt came last.
+        if run.payloads.is_empty() {
+            run.scraped_at = archived.scraped_at;
+        }
+        run.payloads
+            .insert((archived.location, archived.test_type), archived.payload);
+        summary.payloads += 1;
+    }
+    run.write_entry(&mut output, &mut summary)?;
+
+    output
+        .flush()
+        .map_err(|e| format!("Failed to write '{}': {}", output_path, e))?;
+
+    info!(
+        "Reprocessed {} payloads from {} runs into '{}'",
+        summary.payloads, summary.runs, output_path
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOAD: &str = r#"{"ajaxresult":{"slots":{"listTimeSlot":[{"availability":true,"startTime":"11/03/2026 14:15"},{"availability":false,"startTime":"11/03/2026 15:00","duration":45}]}}}"#;
+
+    fn line(run_id: &str, scraped_at: &str, location: &str, payload: &str) -> String {
+        serde_json::to_string(&ArchivedPayload {
+            run_id: run_id.to_string(),
+            scraped_at: scraped_at.to_string(),
+            location: location.to_string(),
+            test_type: TestType::Car,
+            payload: payload.to_string(),
+        })
+        .unwrap()
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("{}-{}", name, std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn reprocesses_one_history_entry_per_run() {
+        let archive = temp_path("payloads.jsonl");
+        let output = temp_path("history.jsonl");
+        fs::write(
+            &archive,
+            [
+                line("run-1", "2026-03-01T00:00:00+00:00", "21", PAYLOAD),
+                line("run-1", "2026-03-01T00:00:05+00:00", "68", "{}"),
+                "not json".to_string(),
+                line("run-2", "2026-03-02T00:00:00+00:00", "21", "{}"),
+                line("run-2", "2026-03-02T00:00:00+00:00", "68", "invalid"),
+                // A retry of 21 later in the run replaces its first payload.
+                line("run-2", "2026-03-02T00:01:00+00:00", "21", PAYLOAD),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let summary = reprocess_payloads(&archive, &output).unwrap();
+        assert_eq!(summary.runs, 2);
+        assert_eq!(summary.payloads, 5);
+        assert_eq!(summary.invalid, 2);
+        assert_eq!(
+            summary.unknown_fields.into_iter().collect::<Vec<_>>(),
+            vec!["ajaxresult.slots.listTimeSlot[].duration"]
+        );
+
+        let entries: Vec<HistoryEntry> = fs::read_to_string(&output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].scraped_at, "2026-03-01T00:00:00+00:00");
+        assert_eq!(entries[0].results.len(), 2);
+        assert_eq!(entries[1].scraped_at, "2026-03-02T00:00:00+00:00");
+        assert_eq!(entries[1].results.len(), 1);
+        // Only the available slot is kept.
+        assert_eq!(entries[1].results[0].slots.len(), 1);
+
+        fs::remove_file(&archive).unwrap();
+        fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn prunes_payloads_past_retention() {
+        let archive = temp_path("payloads-prune.jsonl");
+        let now = DateTime::parse_from_rfc3339("2026-03-31T00:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        let kept = line("run-2", "2026-03-30T00:00:00+00:00", "21", "{}");
+        fs::write(
+            &archive,
+            format!(
+                "{}\n{}\n",
+                line("run-1", "2026-01-01T00:00:00+00:00", "21", "{}"),
+                kept
+            ),
+        )
+        .unwrap();
+
+        prune_archive(&archive, 30, now).unwrap();
+        assert_eq!(fs::read_to_string(&archive).unwrap(), format!("{}\n", kept));
+
+        // Nothing left to expire leaves the file alone.
+        prune_archive(&archive, 30, now).unwrap();
+        assert_eq!(fs::read_to_string(&archive).unwrap(), format!("{}\n", kept));
+
+        fs::remove_file(&archive).unwrap();
+        prune_archive(&archive, 30, now).unwrap();
+    }
+}
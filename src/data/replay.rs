@@ -0,0 +1,413 @@
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::scraper::{
+    BlockedProxy, LocationFailure, ScrapeError, ScrapeFuture, ScrapeOutput, Scraper,
+};
+use super::shared_booking::{LocationBookings, TestType};
+use super::store::write_atomic;
+use crate::settings::Settings;
+
+/// What happened to one location in one scrape attempt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FixtureOutcome {
+    /// The raw `timeslots` payload, replayed through the same parser as a live scrape.
+    Payload { payload: String },
+    /// Parsed bookings, from fixtures recorded before payloads were kept or from backends
+    /// that don't read RTA.
+    Ok { bookings: LocationBookings },
+    /// The location was not returned, e.g. a selector timed out. Older fixtures have no
+    /// `failure` recorded.
+    Failed {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        failure: Option<LocationFailure>,
+    },
+    /// The proxy used for this location was blocked.
+    Blocked {
+        status_code: u16,
+        response_body: String,
+    },
+    /// The whole scrape call errors, as when the Python scraper raises.
+    Error { message: String },
+}
+
+/// Recorded attempts for a single location, replayed in order. Once exhausted the last
+/// attempt repeats, so a fixture with `[failed, ok]` fails once and then always succeeds.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocationFixture {
+    pub location: String,
+    pub attempts: Vec<FixtureOutcome>,
+}
+
+/// Car fixtures are `<location>.json`, other test types `<location>-<test_type>.json`.
+fn fixture_key(location: &str, test_type: TestType) -> String {
+    match test_type {
+        TestType::Car => location.to_string(),
+        _ => format!("{}-{}", location, test_type.as_str()),
+    }
+}
+
+fn fixture_path(dir: &Path, key: &str) -> PathBuf {
+    dir.join(format!("{}.json", key))
+}
+
+fn read_fixture(dir: &Path, key: &str) -> Result<Option<LocationFixture>, String> {
+    let path = fixture_path(dir, key);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read fixture '{}': {}", path.display(), e))?;
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse fixture '{}': {}", path.display(), e))
+}
+
+/// Wraps another scraper and appends each location's outcome to a fixture file.
+pub struct RecordingScraper {
+    inner: Box<dyn Scraper>,
+    dir: PathBuf,
+}
+
+impl RecordingScraper {
+    pub fn new(inner: Box<dyn Scraper>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+        }
+    }
+
+    fn record(
+        &self,
+        locations: &[String],
+        test_type: TestType,
+        output: &ScrapeOutput,
+    ) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create fixture dir: {}", e))?;
+
+        for location in locations {
+            let blocked = output
+                .blocked_proxies
+                .iter()
+                .find(|blocked| blocked.locations.contains(location));
+
+            let outcome = if let Some(payload) = output.payloads.get(location) {
+                FixtureOutcome::Payload {
+                    payload: payload.clone(),
+                }
+            } else if let Some(bookings) = output.bookings.get(location) {
+                FixtureOutcome::Ok {
+                    bookings: bookings.clone(),
+                }
+            } else if let Some(failure) = output.failures.get(location) {
+                FixtureOutcome::Failed {
+                    failure: Some(failure.clone()),
+                }
+            } else if let Some(blocked) = blocked {
+                FixtureOutcome::Blocked {
+                    status_code: blocked.status_code,
+                    response_body: blocked.response_body.clone(),
+                }
+            } else {
+                FixtureOutcome::Failed { failure: None }
+            };
+
+            let key = fixture_key(location, test_type);
+            let mut fixture = read_fixture(&self.dir, &key)?.unwrap_or_else(|| LocationFixture {
+                location: location.clone(),
+                attempts: Vec::new(),
+            });
+            fixture.attempts.push(outcome);
+
+            let json = serde_json::to_string_pretty(&fixture)
+                .map_err(|e| format!("Failed to serialize fixture: {}", e))?;
+            write_atomic(&fixture_path(&self.dir, &key), json.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Scraper for RecordingScraper {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn requires_proxies(&self) -> bool {
+        self.inner.requires_proxies()
+    }
+
+    fn scrape<'a>(
+        &'a self,
+        locations: Vec<String>,
+        test_type: TestType,
+        settings: &'a Settings,
+        proxies: Vec<String>,
+        artifact_dir: Option<String>,
+    ) -> ScrapeFuture<'a> {
+        Box::pin(async move {
+            let output = self
+                .inner
+                .scrape(
+                    locations.clone(),
+                    test_type,
+                    settings,
+                    proxies,
+                    artifact_dir,
+                )
+                .await?;
+
+            match self.record(&locations, test_type, &output) {
+                Ok(_) => info!(
+                    "Recorded {} location fixtures to {}",
+                    locations.len(),
+                    self.dir.display()
+                ),
+                Err(e) => error!("Failed to record fixtures: {}", e),
+            }
+
+            Ok(output)
+        })
+    }
+}
+
+/// Serves previously recorded fixtures instead of scraping, for development without
+/// credentials, proxies or a browser.
+pub struct ReplayScraper {
+    dir: PathBuf,
+    attempts: Mutex<HashMap<String, usize>>,
+}
+
+impl ReplayScraper {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn next_outcome(&self, key: &str) -> Result<Option<FixtureOutcome>, String> {
+        let Some(fixture) = read_fixture(&self.dir, key)? else {
+            return Ok(None);
+        };
+
+        let mut attempts = self.attempts.lock().unwrap();
+        let attempt = attempts.entry(key.to_string()).or_insert(0);
+        let outcome = fixture
+            .attempts
+            .get(*attempt)
+            .or_else(|| fixture.attempts.last())
+            .cloned();
+        *attempt += 1;
+
+        Ok(outcome)
+    }
+}
+
+impl Scraper for ReplayScraper {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn requires_proxies(&self) -> bool {
+        false
+    }
+
+    fn scrape<'a>(
+        &'a self,
+        locations: Vec<String>,
+        test_type: TestType,
+        _settings: &'a Settings,
+        proxies: Vec<String>,
+        _artifact_dir: Option<String>,
+    ) -> ScrapeFuture<'a> {
+        Box::pin(async move {
+            let mut output = ScrapeOutput::default();
+
+            for location in locations {
+                let key = fixture_key(&location, test_type);
+                let outcome = self.next_outcome(&key).map_err(ScrapeError)?;
+
+                match outcome {
+                    Some(FixtureOutcome::Payload { payload }) => {
+                        output.add_payload(&location, test_type, payload);
+                    }
+                    Some(FixtureOutcome::Ok { mut bookings }) => {
+                        bookings.test_type = test_type;
+                        output.bookings.insert(location, bookings);
+                    }
+                    Some(FixtureOutcome::Blocked {
+                        status_code,
+                        response_body,
+                    }) => {
+                        let proxy = proxies
+                            .first()
+                            .cloned()
+                            .unwrap_or_else(|| "replay".to_string());
+                        output.add_blocked(BlockedProxy {
+                            proxy,
+                            status_code,
+                            response_body,
+                            locations: vec![location],
+                        });
+                    }
+                    Some(FixtureOutcome::Error { message }) => {
+                        return Err(ScrapeError(message));
+                    }
+                    Some(FixtureOutcome::Failed { failure }) => {
+                        if let Some(failure) = failure {
+                            output.failures.insert(location, failure);
+                        }
+                    }
+                    None => warn!("No replay fixture for location {}", key),
+                }
+            }
+
+            Ok(output)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::runs::{LocationStatus, ScrapeRun};
+    use crate::data::scraper::FailureCategory;
+    use chrono::Utc;
+
+    fn settings() -> Settings {
+        serde_yaml::from_str(
+            "headless: true
+username: ''
+password: ''
+have_booking: false
+element_timeout: 1000
+retries: 3
+scrape_refresh_time_min: 240
+proxy_path: ''
+parallel_browsers: 1
+scraping_enabled: true
+storage_backend: json
+scraper_backend: replay
+fixtures_dir: dev/fixtures",
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn replays_each_attempt_from_the_fixtures() {
+        let locations: Vec<String> = ["21", "68", "96"].map(String::from).to_vec();
+        let scraper = ReplayScraper::new("dev/fixtures");
+        let settings = settings();
+        let mut run = ScrapeRun::start(scraper.name(), TestType::Car, &locations);
+        let mut bookings = HashMap::new();
+        let mut remaining = locations.clone();
+
+        for attempt in 1..=settings.retries {
+            if remaining.is_empty() {
+                break;
+            }
+            let output = scraper
+                .scrape(
+                    remaining.clone(),
+                    TestType::Car,
+                    &settings,
+                    Vec::new(),
+                    None,
+                )
+                .await
+                .unwrap();
+            run.record_attempt(attempt, Utc::now(), &remaining, &[], Ok(&output));
+            remaining.retain(|location| !output.bookings.contains_key(location));
+            bookings.extend(output.bookings);
+
+            if attempt == 1 {
+                // 68 is blocked and 96 times out on the first attempt.
+                assert_eq!(remaining, ["68", "96"]);
+                assert_eq!(run.locations[1].status, LocationStatus::Blocked);
+                assert_eq!(run.locations[2].status, LocationStatus::SelectorFailure);
+            }
+        }
+
+        // Both succeed on the second.
+        assert_eq!(run.attempts.len(), 2);
+        let attempts: Vec<(&str, LocationStatus, u32)> = run
+            .locations
+            .iter()
+            .map(|outcome| (outcome.location.as_str(), outcome.status, outcome.attempts))
+            .collect();
+        assert_eq!(
+            attempts,
+            [
+                ("21", LocationStatus::Ok, 1),
+                ("68", LocationStatus::Ok, 2),
+                ("96", LocationStatus::Ok, 2),
+            ]
+        );
+
+        assert!(!bookings["21"].slots.is_empty());
+        assert!(!bookings["68"].slots.is_empty());
+        assert!(bookings["96"].slots.is_empty());
+    }
+
+    #[test]
+    fn records_blocks_only_for_their_locations() {
+        let dir = std::env::temp_dir().join(format!("record-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let recorder = RecordingScraper::new(Box::new(ReplayScraper::new(&dir)), &dir);
+
+        let mut output = ScrapeOutput::default();
+        output.add_payload("21", TestType::Car, "{}".into());
+        output.add_blocked(BlockedProxy {
+            proxy: "http://proxy:8080".into(),
+            status_code: 403,
+            response_body: "Access Denied".into(),
+            locations: vec!["68".into()],
+        });
+        output.failures.insert(
+            "96".into(),
+            LocationFailure {
+                category: FailureCategory::ElementTimeout,
+                message: "timeout".into(),
+                duration_ms: None,
+                artifacts: None,
+            },
+        );
+
+        let locations: Vec<String> = ["21", "68", "96", "99"].map(String::from).to_vec();
+        recorder.record(&locations, TestType::Car, &output).unwrap();
+
+        let outcome = |location: &str| {
+            read_fixture(&dir, location)
+                .unwrap()
+                .unwrap()
+                .attempts
+                .remove(0)
+        };
+        assert!(matches!(outcome("21"), FixtureOutcome::Payload { .. }));
+        assert!(matches!(
+            outcome("68"),
+            FixtureOutcome::Blocked {
+                status_code: 403,
+                ..
+            }
+        ));
+        assert!(matches!(
+            outcome("96"),
+            FixtureOutcome::Failed { failure: Some(_) }
+        ));
+        assert!(matches!(
+            outcome("99"),
+            FixtureOutcome::Failed { failure: None }
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,50 +3,30 @@ use pyo3::types::PyModule;
 use std::collections::HashMap;
 use std::ffi::CString;
 
-use super::discord::notify_403_blocked;
-use super::shared_booking::LocationBookings;
+use super::scraper::{
+    BlockedProxy, LocationFailure, ScrapeError, ScrapeFuture, ScrapeOutput, Scraper,
+};
+use super::shared_booking::TestType;
 use crate::settings::Settings;
 
-const SCRAPER_PY: &str = include_str!("scraper.py");
-
-#[derive(Debug, FromPyObject)]
-#[pyo3(from_item_all)]
-pub struct BlockedProxy {
-    pub proxy: String,
-    pub status_code: u16,
-    pub response_body: String,
-}
+pub const SCRAPER_PY: &str = include_str!("scraper.py");
 
 #[derive(Debug, FromPyObject)]
 #[pyo3(from_item_all)]
 struct ScrapeResult {
-    bookings: HashMap<String, LocationBookings>,
+    /// Raw `timeslots` JSON per location, parsed on our side.
+    payloads: HashMap<String, String>,
     blocked_proxies: Vec<BlockedProxy>,
+    failures: HashMap<String, LocationFailure>,
+    durations_ms: HashMap<String, u64>,
 }
 
-#[derive(Debug)]
-pub struct ScrapeError(String);
-
-impl std::fmt::Display for ScrapeError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "ScrapeError: {}", self.0)
-    }
-}
-
-impl std::error::Error for ScrapeError {}
-
 impl From<PyErr> for ScrapeError {
     fn from(err: PyErr) -> Self {
         ScrapeError(err.to_string())
     }
 }
 
-impl From<tokio::task::JoinError> for ScrapeError {
-    fn from(err: tokio::task::JoinError) -> Self {
-        ScrapeError(err.to_string())
-    }
-}
-
 fn scrape_single_group(
     locations: Vec<String>,
     headless: bool,
@@ -56,6 +36,8 @@ fn scrape_single_group(
     timeout_ms: u64,
     proxies: Vec<String>,
     parallel_browsers: usize,
+    test_type: TestType,
+    artifact_dir: Option<String>,
 ) -> Result<ScrapeResult, ScrapeError> {
     pyo3_pylogger::register("rta_scraper");
 
@@ -75,6 +57,9 @@ fn scrape_single_group(
             timeout_ms,
             proxies,
             parallel_browsers,
+            test_type.as_str(),
+            py.None(),
+            artifact_dir,
         ))?;
 
         let scrape_result: ScrapeResult = result.extract()?;
@@ -83,15 +68,44 @@ fn scrape_single_group(
     })
 }
 
+/// Runs `scraper.py` in the embedded Python interpreter.
+pub struct PyScraper;
+
+impl Scraper for PyScraper {
+    fn name(&self) -> &'static str {
+        "python"
+    }
+
+    fn scrape<'a>(
+        &'a self,
+        locations: Vec<String>,
+        test_type: TestType,
+        settings: &'a Settings,
+        proxies: Vec<String>,
+        artifact_dir: Option<String>,
+    ) -> ScrapeFuture<'a> {
+        Box::pin(scrape_rta_timeslots(
+            locations,
+            test_type,
+            settings,
+            proxies,
+            artifact_dir,
+        ))
+    }
+}
+
 pub async fn scrape_rta_timeslots(
     locations: Vec<String>,
+    test_type: TestType,
     settings: &Settings,
     proxies: Vec<String>,
-) -> Result<HashMap<String, LocationBookings>, Box<dyn std::error::Error + Send + Sync>> {
+    artifact_dir: Option<String>,
+) -> Result<ScrapeOutput, ScrapeError> {
     let parallel_browsers = settings.parallel_browsers;
     
     log::info!(
-        "Starting scrape with {} parallel browsers for {} locations",
+        "Starting {} scrape with {} parallel browsers for {} locations",
+        test_type.as_str(),
         parallel_browsers,
         locations.len()
     );
@@ -112,24 +126,27 @@ pub async fn scrape_rta_timeslots(
             timeout_ms,
             proxies,
             parallel_browsers,
+            test_type,
+            artifact_dir,
         )
     })
     .await??;
     
-    if let Some(webhook_url) = &settings.webhook_url {
-        for blocked in &result.blocked_proxies {
-            if let Err(e) = notify_403_blocked(
-                webhook_url,
-                &blocked.proxy,
-                blocked.status_code,
-                &blocked.response_body,
-            ).await {
-                log::error!("Failed to send Discord notification: {}", e);
-            }
-        }
+    let mut output = ScrapeOutput {
+        blocked_proxies: result.blocked_proxies,
+        failures: result.failures,
+        durations_ms: result.durations_ms,
+        ..Default::default()
+    };
+    for (location, payload) in result.payloads {
+        output.add_payload(&location, test_type, payload);
     }
-    
-    log::info!("Scraping complete: {} locations scraped.", result.bookings.len());
 
-    Ok(result.bookings)
+    log::info!(
+        "Scraping complete: {} locations scraped, {} failed.",
+        output.bookings.len(),
+        output.failures.len()
+    );
+
+    Ok(output)
 }
@@ -0,0 +1,473 @@
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::{Australia::Sydney, Tz};
+use rand::Rng;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use super::shared_booking::{BookingData, TestType};
+use crate::settings::Settings;
+
+static NEXT_RUN: OnceLock<Arc<RwLock<Option<DateTime<Utc>>>>> = OnceLock::new();
+
+fn get_next_run() -> &'static Arc<RwLock<Option<DateTime<Utc>>>> {
+    NEXT_RUN.get_or_init(|| Arc::new(RwLock::new(None)))
+}
+
+/// When the background task next plans to start a run, if it is running.
+pub fn next_planned_run() -> Option<DateTime<Utc>> {
+    *get_next_run().read().unwrap()
+}
+
+/// Shortest pause between scheduler wake-ups, so failing locations aren't retried in a loop.
+const MIN_WAIT_SECS: i64 = 60;
+/// Locations coming due this soon after a run starts are scraped in that run.
+const DUE_SLACK_MINUTES: i64 = 10;
+
+struct QuietWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietWindow {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// The first end of this window after `local`. An end in the hour skipped when daylight
+    /// saving starts is reached as soon as the clocks go forward.
+    fn next_end(&self, local: DateTime<Tz>) -> DateTime<Tz> {
+        let mut date = local.date_naive();
+        if local.time() >= self.end {
+            date = date.succ_opt().unwrap_or(date);
+        }
+        let end = date.and_time(self.end);
+        (0..=60)
+            .find_map(|minutes| {
+                Sydney
+                    .from_local_datetime(&(end + Duration::minutes(minutes)))
+                    .earliest()
+            })
+            .unwrap_or(local + Duration::hours(1))
+    }
+}
+
+fn parse_quiet_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|e| format!("Invalid quiet hours time '{}': {}", value, e))
+}
+
+/// Decides which locations are due for a scrape and when the next run starts. A location is
+/// due for a test type once its refresh interval has passed since it was last scraped or last
+/// attempted for that test type, whichever is later.
+pub struct Scheduler {
+    locations: Vec<String>,
+    test_types: Vec<TestType>,
+    last_attempted: HashMap<(TestType, String), DateTime<Utc>>,
+    cron: Option<cron::Schedule>,
+    quiet_hours: Vec<QuietWindow>,
+}
+
+impl Scheduler {
+    pub fn new(locations: Vec<String>, settings: &Settings) -> Result<Self, String> {
+        let cron = settings
+            .scrape_schedule
+            .as_deref()
+            .map(|expression| {
+                cron::Schedule::from_str(expression)
+                    .map_err(|e| format!("Invalid scrape_schedule '{}': {}", expression, e))
+            })
+            .transpose()?;
+
+        let quiet_hours = settings
+            .quiet_hours
+            .iter()
+            .map(|window| {
+                Ok(QuietWindow {
+                    start: parse_quiet_time(&window.start)?,
+                    end: parse_quiet_time(&window.end)?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if settings.test_types.is_empty() {
+            return Err("No test types configured".to_string());
+        }
+
+        Ok(Self {
+            locations,
+            test_types: settings.test_types.clone(),
+            last_attempted: HashMap::new(),
+            cron,
+            quiet_hours,
+        })
+    }
+
+    /// With a cron schedule, unlisted locations are scraped on every run.
+    fn refresh_interval(&self, settings: &Settings, location: &str) -> Duration {
+        match settings.location_schedule(location) {
+            Some(schedule) => Duration::minutes(schedule.refresh_min as i64),
+            None if self.cron.is_some() => Duration::zero(),
+            None => Duration::minutes(settings.scrape_refresh_time_min as i64),
+        }
+    }
+
+    fn priority(settings: &Settings, location: &str) -> i32 {
+        settings
+            .location_schedule(location)
+            .map(|schedule| schedule.priority)
+            .unwrap_or(0)
+    }
+
+    fn last_run(
+        &self,
+        data: &BookingData,
+        test_type: TestType,
+        location: &str,
+    ) -> Option<DateTime<Utc>> {
+        let scraped = data
+            .location(location, test_type)
+            .and_then(|booking| booking.last_scraped.as_deref())
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&Utc));
+
+        let attempted = self
+            .last_attempted
+            .get(&(test_type, location.to_string()))
+            .copied();
+        scraped.max(attempted)
+    }
+
+    fn next_due(
+        &self,
+        settings: &Settings,
+        data: &BookingData,
+        test_type: TestType,
+        location: &str,
+    ) -> Option<DateTime<Utc>> {
+        self.last_run(data, test_type, location)
+            .map(|last_run| last_run + self.refresh_interval(settings, location))
+    }
+
+    pub fn test_types(&self) -> &[TestType] {
+        &self.test_types
+    }
+
+    /// Locations due for `test_type` at `now`, highest priority first and then most overdue
+    /// first.
+    pub fn due_locations(
+        &self,
+        settings: &Settings,
+        data: &BookingData,
+        test_type: TestType,
+        now: DateTime<Utc>,
+    ) -> Vec<String> {
+        let cutoff = now + Duration::minutes(DUE_SLACK_MINUTES);
+
+        let mut due: Vec<(i32, Option<DateTime<Utc>>, &String)> = self
+            .locations
+            .iter()
+            .filter_map(|location| {
+                let next_due = self.next_due(settings, data, test_type, location);
+                if next_due.is_some_and(|next_due| next_due > cutoff) {
+                    return None;
+                }
+                Some((Self::priority(settings, location), next_due, location))
+            })
+            .collect();
+
+        due.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let limit = settings.max_locations_per_run.unwrap_or(usize::MAX);
+        due.into_iter()
+            .take(limit)
+            .map(|(_, _, location)| location.clone())
+            .collect()
+    }
+
+    pub fn mark_attempted(
+        &mut self,
+        test_type: TestType,
+        locations: &[String],
+        now: DateTime<Utc>,
+    ) {
+        for location in locations {
+            self.last_attempted
+                .insert((test_type, location.clone()), now);
+        }
+    }
+
+    /// Without a cron schedule the first run starts right away; with one it waits for the
+    /// first scheduled time.
+    pub fn runs_on_start(&self) -> bool {
+        self.cron.is_none()
+    }
+
+    pub fn is_quiet(&self, time: DateTime<Utc>) -> bool {
+        let local = time.with_timezone(&Sydney).time();
+        self.quiet_hours.iter().any(|window| window.contains(local))
+    }
+
+    /// Moves `time` past any quiet window it falls in.
+    fn skip_quiet_hours(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let mut local = time.with_timezone(&Sydney);
+
+        // Windows may overlap or chain, so keep going until none applies.
+        for _ in 0..=self.quiet_hours.len() {
+            match self
+                .quiet_hours
+                .iter()
+                .find(|window| window.contains(local.time()))
+            {
+                Some(window) => local = window.next_end(local),
+                None => break,
+            }
+        }
+
+        local.with_timezone(&Utc)
+    }
+
+    fn next_due_time(
+        &self,
+        settings: &Settings,
+        data: &BookingData,
+        now: DateTime<Utc>,
+    ) -> DateTime<Utc> {
+        let next_due = self
+            .test_types
+            .iter()
+            .flat_map(|test_type| {
+                self.locations
+                    .iter()
+                    .map(move |location| self.next_due(settings, data, *test_type, location))
+            })
+            .min()
+            .flatten()
+            .unwrap_or(now);
+
+        next_due.max(now + Duration::seconds(MIN_WAIT_SECS))
+    }
+
+    /// Plans the next run from the cron schedule, or from when the next location is due,
+    /// then applies jitter and quiet hours. The result is published for `next_planned_run`.
+    pub fn plan_next_run(
+        &self,
+        settings: &Settings,
+        data: &BookingData,
+        now: DateTime<Utc>,
+    ) -> DateTime<Utc> {
+        let mut planned = match &self.cron {
+            Some(schedule) => schedule
+                .after(&now.with_timezone(&Sydney))
+                .next()
+                .map(|time| time.with_timezone(&Utc))
+                .unwrap_or(now + Duration::days(1)),
+            None => self.next_due_time(settings, data, now),
+        };
+
+        if settings.schedule_jitter_min > 0 {
+            let jitter_secs = rand::rng().random_range(0..settings.schedule_jitter_min * 60);
+            planned += Duration::seconds(jitter_secs as i64);
+        }
+
+        let planned = self.skip_quiet_hours(planned);
+        *get_next_run().write().unwrap() = Some(planned);
+        planned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(start: &str, end: &str) -> QuietWindow {
+        QuietWindow {
+            start: parse_quiet_time(start).unwrap(),
+            end: parse_quiet_time(end).unwrap(),
+        }
+    }
+
+    fn time(value: &str) -> NaiveTime {
+        parse_quiet_time(value).unwrap()
+    }
+
+    /// A Sydney local time such as `2026-04-04 23:30`, in UTC.
+    fn sydney(value: &str) -> DateTime<Utc> {
+        let naive = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap();
+        Sydney
+            .from_local_datetime(&naive)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    fn settings(extra: &str) -> Settings {
+        serde_yaml::from_str(&format!(
+            "headless: true
+username: ''
+password: ''
+have_booking: false
+element_timeout: 1000
+retries: 1
+scrape_refresh_time_min: 240
+proxy_path: ''
+parallel_browsers: 1
+scraping_enabled: true
+{}",
+            extra
+        ))
+        .unwrap()
+    }
+
+    fn scheduler(settings: &Settings) -> Scheduler {
+        Scheduler::new(vec!["21".to_string()], settings).unwrap()
+    }
+
+    #[test]
+    fn windows_contain_their_start_but_not_their_end() {
+        let cases = [
+            ("09:00", "17:00", "08:59", false),
+            ("09:00", "17:00", "09:00", true),
+            ("09:00", "17:00", "16:59", true),
+            ("09:00", "17:00", "17:00", false),
+            ("23:00", "05:00", "22:59", false),
+            ("23:00", "05:00", "23:00", true),
+            ("23:00", "05:00", "00:00", true),
+            ("23:00", "05:00", "04:59", true),
+            ("23:00", "05:00", "05:00", false),
+            ("23:00", "05:00", "12:00", false),
+        ];
+        for (start, end, at, expected) in cases {
+            assert_eq!(
+                window(start, end).contains(time(at)),
+                expected,
+                "{}-{} at {}",
+                start,
+                end,
+                at
+            );
+        }
+    }
+
+    #[test]
+    fn next_end_is_today_or_tomorrow() {
+        let overnight = window("23:00", "05:00");
+        let local = |value| sydney(value).with_timezone(&Sydney);
+
+        assert_eq!(
+            overnight.next_end(local("2026-02-10 23:30")),
+            local("2026-02-11 05:00")
+        );
+        assert_eq!(
+            overnight.next_end(local("2026-02-11 01:00")),
+            local("2026-02-11 05:00")
+        );
+    }
+
+    #[test]
+    fn skips_overnight_quiet_hours() {
+        let settings = settings("quiet_hours: [{start: '23:00', end: '05:00'}]");
+        let scheduler = scheduler(&settings);
+
+        assert_eq!(
+            scheduler.skip_quiet_hours(sydney("2026-02-10 23:30")),
+            sydney("2026-02-11 05:00")
+        );
+        assert_eq!(
+            scheduler.skip_quiet_hours(sydney("2026-02-11 04:59")),
+            sydney("2026-02-11 05:00")
+        );
+        assert_eq!(
+            scheduler.skip_quiet_hours(sydney("2026-02-11 05:00")),
+            sydney("2026-02-11 05:00")
+        );
+        assert_eq!(
+            scheduler.skip_quiet_hours(sydney("2026-02-11 22:59")),
+            sydney("2026-02-11 22:59")
+        );
+    }
+
+    #[test]
+    fn skips_back_to_back_windows() {
+        let settings = settings(
+            "quiet_hours:
+  - {start: '22:00', end: '23:00'}
+  - {start: '23:00', end: '01:00'}
+  - {start: '01:00', end: '02:00'}",
+        );
+        let scheduler = scheduler(&settings);
+
+        assert_eq!(
+            scheduler.skip_quiet_hours(sydney("2026-02-10 22:30")),
+            sydney("2026-02-11 02:00")
+        );
+        assert!(scheduler.is_quiet(sydney("2026-02-10 23:00")));
+        assert!(!scheduler.is_quiet(sydney("2026-02-11 02:00")));
+    }
+
+    #[test]
+    fn quiet_hours_follow_sydney_daylight_saving() {
+        let settings = settings("quiet_hours: [{start: '23:00', end: '05:00'}]");
+        let scheduler = scheduler(&settings);
+
+        // Daylight saving ends on 5 April 2026, so the night is an hour longer.
+        assert_eq!(
+            scheduler.skip_quiet_hours(sydney("2026-04-04 23:30")),
+            utc("2026-04-04T19:00:00Z")
+        );
+        // It starts on 4 October 2026, so the night is an hour shorter.
+        assert_eq!(
+            scheduler.skip_quiet_hours(sydney("2026-10-03 23:30")),
+            utc("2026-10-03T18:00:00Z")
+        );
+    }
+
+    #[test]
+    fn window_ending_in_the_skipped_hour_ends_when_clocks_go_forward() {
+        // 2:00 to 3:00 doesn't exist in Sydney on 4 October 2026.
+        let settings = settings("quiet_hours: [{start: '01:00', end: '02:30'}]");
+        let scheduler = scheduler(&settings);
+
+        assert_eq!(
+            scheduler.skip_quiet_hours(sydney("2026-10-04 01:30")),
+            utc("2026-10-03T16:00:00Z")
+        );
+    }
+
+    #[test]
+    fn plans_runs_around_quiet_hours() {
+        let data = BookingData::default();
+
+        // Nothing scraped yet, so the location is due after the minimum wait.
+        let quiet = settings("quiet_hours: [{start: '23:00', end: '05:00'}]");
+        let planner = scheduler(&quiet);
+        assert_eq!(
+            planner.plan_next_run(&quiet, &data, sydney("2026-02-10 12:00")),
+            sydney("2026-02-10 12:01")
+        );
+        assert_eq!(
+            planner.plan_next_run(&quiet, &data, sydney("2026-02-10 22:59")),
+            sydney("2026-02-11 05:00")
+        );
+
+        // Cron times are Sydney times, across the end of daylight saving too.
+        let cron = settings(
+            "scrape_schedule: '0 0 4 * * *'
+quiet_hours: [{start: '03:30', end: '04:30'}]",
+        );
+        let planner = scheduler(&cron);
+        assert!(!planner.runs_on_start());
+        assert_eq!(
+            planner.plan_next_run(&cron, &data, sydney("2026-04-04 12:00")),
+            utc("2026-04-04T18:30:00Z")
+        );
+    }
+}
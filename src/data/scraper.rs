@@ -0,0 +1,230 @@
+use pyo3::prelude::*;
+use pyo3::FromPyObject;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+
+use super::demo::DemoScraper;
+use super::jobs::WorkerQueueScraper;
+use super::replay::{RecordingScraper, ReplayScraper};
+use super::rta::PyScraper;
+use super::shared_booking::{LocationBookings, TestType};
+use super::subprocess::SubprocessScraper;
+use super::timeslots::parse_timeslots;
+use crate::settings::{ScraperBackend, Settings};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromPyObject)]
+#[pyo3(from_item_all)]
+pub struct BlockedProxy {
+    pub proxy: String,
+    pub status_code: u16,
+    pub response_body: String,
+    /// Locations the blocked session didn't get to.
+    #[pyo3(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<String>,
+}
+
+/// Why the scraper gave up on a location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCategory {
+    LoginFailure,
+    ElementTimeout,
+    /// The page loaded but was neither a results page with a `timeslots` variable nor one
+    /// saying the centre has no slots.
+    MissingTimeslots,
+    NavigationError,
+}
+
+impl FailureCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureCategory::LoginFailure => "login_failure",
+            FailureCategory::ElementTimeout => "element_timeout",
+            FailureCategory::MissingTimeslots => "missing_timeslots",
+            FailureCategory::NavigationError => "navigation_error",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "login_failure" => Some(FailureCategory::LoginFailure),
+            "element_timeout" => Some(FailureCategory::ElementTimeout),
+            "missing_timeslots" => Some(FailureCategory::MissingTimeslots),
+            "navigation_error" => Some(FailureCategory::NavigationError),
+            _ => None,
+        }
+    }
+
+    /// Whether trying the location again in the same run is likely to help. A centre with
+    /// no timeslots on its page will look the same a few seconds later.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, FailureCategory::MissingTimeslots)
+    }
+}
+
+fn extract_failure_category(obj: &Bound<'_, PyAny>) -> PyResult<FailureCategory> {
+    let value: String = obj.extract()?;
+    // Unknown categories from a newer scraper.py are still failures worth retrying.
+    Ok(FailureCategory::parse(&value).unwrap_or(FailureCategory::NavigationError))
+}
+
+/// Debugging evidence the scraper saved when a location failed. Missing entries could not
+/// be captured.
+#[derive(Debug, Clone, Serialize, Deserialize, FromPyObject)]
+#[pyo3(from_item_all)]
+pub struct FailureArtifacts {
+    pub url: Option<String>,
+    pub screenshot: Option<String>,
+    pub html: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromPyObject)]
+#[pyo3(from_item_all)]
+pub struct LocationFailure {
+    #[pyo3(from_py_with = extract_failure_category)]
+    pub category: FailureCategory,
+    pub message: String,
+    #[pyo3(default)]
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[pyo3(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifacts: Option<FailureArtifacts>,
+}
+
+/// Everything a single scrape attempt produced, keyed by location ID. Workers post it back
+/// to the primary as JSON.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrapeOutput {
+    pub bookings: HashMap<String, LocationBookings>,
+    pub blocked_proxies: Vec<BlockedProxy>,
+    /// Locations the scraper tried and gave up on, with the reason.
+    pub failures: HashMap<String, LocationFailure>,
+    /// How long each scraped location took, when the backend measures it.
+    pub durations_ms: HashMap<String, u64>,
+    /// Raw `timeslots` JSON per location, from backends that read it from RTA.
+    pub payloads: HashMap<String, String>,
+    /// Fields in `payloads` the parser doesn't know about.
+    pub unknown_fields: BTreeSet<String>,
+}
+
+impl ScrapeOutput {
+    /// Adds the results of a scrape over other locations, such as another worker's job.
+    pub fn merge(&mut self, other: ScrapeOutput) {
+        for (location, bookings) in other.bookings {
+            self.failures.remove(&location);
+            self.bookings.insert(location, bookings);
+        }
+        for (location, failure) in other.failures {
+            if !self.bookings.contains_key(&location) {
+                self.failures.insert(location, failure);
+            }
+        }
+        self.durations_ms.extend(other.durations_ms);
+        self.payloads.extend(other.payloads);
+        self.unknown_fields.extend(other.unknown_fields);
+        for blocked in other.blocked_proxies {
+            self.add_blocked(blocked);
+        }
+    }
+
+    /// Records a blocked proxy once, collecting the locations it was blocked on.
+    pub fn add_blocked(&mut self, blocked: BlockedProxy) {
+        match self
+            .blocked_proxies
+            .iter_mut()
+            .find(|existing| existing.proxy == blocked.proxy)
+        {
+            Some(existing) => existing.locations.extend(blocked.locations),
+            None => self.blocked_proxies.push(blocked),
+        }
+    }
+
+    /// Parses a raw `timeslots` payload into the location's bookings, or records a failure
+    /// if it can't be read. The payload is kept for archiving either way.
+    pub fn add_payload(&mut self, location: &str, test_type: TestType, payload: String) {
+        match parse_timeslots(&payload) {
+            Ok(timeslots) => {
+                self.unknown_fields.extend(timeslots.unknown_fields());
+                self.failures.remove(location);
+                self.bookings.insert(
+                    location.to_string(),
+                    timeslots.into_bookings(location, test_type),
+                );
+            }
+            Err(e) => {
+                self.failures.insert(
+                    location.to_string(),
+                    LocationFailure {
+                        category: FailureCategory::MissingTimeslots,
+                        message: e,
+                        duration_ms: self.durations_ms.get(location).copied(),
+                        artifacts: None,
+                    },
+                );
+            }
+        }
+        self.payloads.insert(location.to_string(), payload);
+    }
+}
+
+#[derive(Debug)]
+pub struct ScrapeError(pub String);
+
+impl std::fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ScrapeError: {}", self.0)
+    }
+}
+
+impl std::error::Error for ScrapeError {}
+
+impl From<tokio::task::JoinError> for ScrapeError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        ScrapeError(err.to_string())
+    }
+}
+
+pub type ScrapeFuture<'a> = Pin<Box<dyn Future<Output = Result<ScrapeOutput, ScrapeError>> + Send + 'a>>;
+
+/// A source of booking data for a set of RTA locations.
+pub trait Scraper: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether `scrape` needs proxies from the proxy file to do anything.
+    fn requires_proxies(&self) -> bool {
+        true
+    }
+
+    /// Scrapes `test_type` availability for as many of `locations` as possible. Locations
+    /// missing from the output are retried by the caller. Backends that drive a browser save
+    /// failure artifacts under `artifact_dir` when it is set.
+    fn scrape<'a>(
+        &'a self,
+        locations: Vec<String>,
+        test_type: TestType,
+        settings: &'a Settings,
+        proxies: Vec<String>,
+        artifact_dir: Option<String>,
+    ) -> ScrapeFuture<'a>;
+}
+
+pub fn create_scraper(settings: &Settings) -> Box<dyn Scraper> {
+    let scraper: Box<dyn Scraper> = match settings.scraper_backend {
+        ScraperBackend::Python => Box::new(PyScraper),
+        ScraperBackend::Subprocess => Box::new(SubprocessScraper),
+        ScraperBackend::Replay => Box::new(ReplayScraper::new(&settings.fixtures_dir)),
+        ScraperBackend::Demo => Box::new(DemoScraper::new()),
+        ScraperBackend::Workers => Box::new(WorkerQueueScraper),
+    };
+
+    if settings.record_fixtures && settings.scraper_backend.is_live() {
+        Box::new(RecordingScraper::new(scraper, &settings.fixtures_dir))
+    } else {
+        scraper
+    }
+}
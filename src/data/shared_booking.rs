@@ -1,18 +1,145 @@
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::{Australia::Sydney, Tz};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::{
     cmp::Ordering,
     hash::{DefaultHasher, Hash, Hasher},
 };
 
+/// Format RTA uses for slot start times, e.g. `20/02/2026 9:50`.
+pub const SLOT_TIME_FORMAT: &str = "%d/%m/%Y %-H:%M";
+
+/// Format of the calendar endpoint's `slot` parameter, a Sydney local start time like
+/// `20260220T1050`. Lives here so the page linking to it can use it too.
+pub const SLOT_PARAM_FORMAT: &str = "%Y%m%dT%H%M";
+
+pub fn parse_slot_time(value: &str) -> Result<DateTime<Tz>, String> {
+    let naive = NaiveDateTime::parse_from_str(value.trim(), "%d/%m/%Y %H:%M")
+        .map_err(|e| format!("Invalid slot time '{}': {}", value, e))?;
+
+    Sydney
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("Slot time '{}' does not exist in Australia/Sydney", value))
+}
+
+mod slot_time {
+    use chrono::DateTime;
+    use chrono_tz::Tz;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &DateTime<Tz>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format(super::SLOT_TIME_FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Tz>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        super::parse_slot_time(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The kind of test a slot is for. RTA books each one through a different test item.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TestType {
+    /// Class C driving test.
+    #[default]
+    Car,
+    /// Motorcycle rider test.
+    Rider,
+    /// Heavy vehicle (LR, MR, HR) driving test.
+    HeavyVehicle,
+}
+
+impl TestType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TestType::Car => "car",
+            TestType::Rider => "rider",
+            TestType::HeavyVehicle => "heavy_vehicle",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "car" => Some(TestType::Car),
+            "rider" => Some(TestType::Rider),
+            "heavy_vehicle" => Some(TestType::HeavyVehicle),
+            _ => None,
+        }
+    }
+
+    pub fn display(&self) -> &'static str {
+        match self {
+            TestType::Car => "Car driving test",
+            TestType::Rider => "Rider test",
+            TestType::HeavyVehicle => "Heavy vehicle test",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Hash)]
-#[cfg_attr(feature = "ssr", derive(pyo3::FromPyObject))]
-#[cfg_attr(feature = "ssr", pyo3(from_item_all))]
 pub struct TimeSlot {
     pub availability: bool,
     pub slot_number: Option<u32>,
-    #[serde(rename = "startTime")]
-    #[cfg_attr(feature = "ssr", pyo3(item("startTime")))]
-    pub start_time: String,
+    #[serde(rename = "startTime", with = "slot_time")]
+    pub start_time: DateTime<Tz>,
+    /// Set on slots that first appeared in the latest scrape.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<SlotLabel>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlotLabel {
+    /// Appeared earlier than the location's previous earliest slot.
+    LikelyCancellation,
+    NewRelease,
+}
+
+impl SlotLabel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SlotLabel::LikelyCancellation => "likely_cancellation",
+            SlotLabel::NewRelease => "new_release",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "likely_cancellation" => Some(SlotLabel::LikelyCancellation),
+            "new_release" => Some(SlotLabel::NewRelease),
+            _ => None,
+        }
+    }
+
+    pub fn display(&self) -> &'static str {
+        match self {
+            SlotLabel::LikelyCancellation => "Likely cancellation",
+            SlotLabel::NewRelease => "New release",
+        }
+    }
+}
+
+impl TimeSlot {
+    pub fn date(&self) -> NaiveDate {
+        self.start_time.date_naive()
+    }
+
+    /// Start time in the same `dd/mm/yyyy h:mm` form RTA displays.
+    pub fn display(&self) -> String {
+        self.start_time.format(SLOT_TIME_FORMAT).to_string()
+    }
+
+    pub fn display_date(&self) -> String {
+        self.start_time.format("%a %d/%m/%Y").to_string()
+    }
+
+    pub fn display_time(&self) -> String {
+        self.start_time.format("%-H:%M").to_string()
+    }
 }
 
 impl PartialEq for TimeSlot {
@@ -31,47 +158,48 @@ impl PartialOrd for TimeSlot {
 
 impl Ord for TimeSlot {
     fn cmp(&self, other: &Self) -> Ordering {
-        // self
-        let self_parts: Vec<&str> = self.start_time.split(' ').collect();
-        let self_date_parts: Vec<u32> = self_parts[0]
-            .split('/')
-            .map(|s| s.parse().unwrap())
-            .collect();
-        let self_time_parts: Vec<u32> = self_parts[1]
-            .split(':')
-            .map(|s| s.parse().unwrap())
-            .collect();
-
-        // other
-        let other_parts: Vec<&str> = other.start_time.split(' ').collect();
-        let other_date_parts: Vec<u32> = other_parts[0]
-            .split('/')
-            .map(|s| s.parse().unwrap())
-            .collect();
-        let other_time_parts: Vec<u32> = other_parts[1]
-            .split(':')
-            .map(|s| s.parse().unwrap())
-            .collect();
-
-        self_date_parts[2]
-            .cmp(&other_date_parts[2])
-            .then(self_date_parts[1].cmp(&other_date_parts[1]))
-            .then(self_date_parts[0].cmp(&other_date_parts[0]))
-            .then(self_time_parts[0].cmp(&other_time_parts[0]))
-            .then(self_time_parts[1].cmp(&other_time_parts[1]))
+        self.start_time.cmp(&other.start_time)
     }
 }
 
+fn deserialize_valid_slots<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<TimeSlot>, D::Error> {
+    let raw = Vec::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .filter_map(|value| match serde_json::from_value::<TimeSlot>(value) {
+            Ok(slot) => Some(slot),
+            Err(e) => {
+                log::warn!("Dropping invalid time slot: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Hash)]
-#[cfg_attr(feature = "ssr", derive(pyo3::FromPyObject))]
-#[cfg_attr(feature = "ssr", pyo3(from_item_all))]
 pub struct LocationBookings {
     pub location: String,
+    /// Data from before test types were tracked is all for the car test.
+    #[serde(default)]
+    pub test_type: TestType,
+    #[serde(deserialize_with = "deserialize_valid_slots")]
     pub slots: Vec<TimeSlot>,
     pub next_available_date: Option<String>,
+    /// When this location was last successfully scraped, as RFC 3339.
+    #[serde(default)]
+    pub last_scraped: Option<String>,
+    /// Set when the latest scrape run failed for this location and `slots` are carried over.
+    #[serde(default)]
+    pub stale: bool,
 }
 
 impl LocationBookings {
+    pub fn is_for(&self, location: &str, test_type: TestType) -> bool {
+        self.location == location && self.test_type == test_type
+    }
+
     pub fn calculate_hash(&self) -> String {
         let mut hasher = DefaultHasher::new();
         self.hash(&mut hasher);
@@ -86,9 +214,116 @@ pub struct BookingData {
 }
 
 impl BookingData {
+    pub fn location(&self, location: &str, test_type: TestType) -> Option<&LocationBookings> {
+        self.results
+            .iter()
+            .find(|booking| booking.is_for(location, test_type))
+    }
+
     pub fn calculate_hash(&self) -> String {
         let mut hasher = DefaultHasher::new();
         self.hash(&mut hasher);
         hasher.finish().to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Offset, Timelike};
+
+    fn slot(start_time: &str) -> TimeSlot {
+        TimeSlot {
+            availability: true,
+            slot_number: None,
+            start_time: parse_slot_time(start_time).unwrap(),
+            label: None,
+        }
+    }
+
+    #[test]
+    fn parses_single_and_double_digit_hours() {
+        let morning = parse_slot_time("20/02/2026 9:05").unwrap();
+        assert_eq!((morning.hour(), morning.minute()), (9, 5));
+        assert_eq!(
+            morning.date_naive(),
+            NaiveDate::from_ymd_opt(2026, 2, 20).unwrap()
+        );
+        assert_eq!(parse_slot_time("20/02/2026 09:05"), Ok(morning));
+
+        let afternoon = parse_slot_time(" 20/02/2026 14:15 ").unwrap();
+        assert_eq!((afternoon.hour(), afternoon.minute()), (14, 15));
+    }
+
+    #[test]
+    fn rejects_invalid_slot_times() {
+        for value in [
+            "",
+            "20/02/2026",
+            "2026-02-20 09:05",
+            "32/01/2026 9:00",
+            "20/02/2026 24:00",
+            "20/02/2026 9:5x",
+            // Clocks in Sydney skip from 2:00 to 3:00 when daylight saving starts.
+            "04/10/2026 2:30",
+        ] {
+            assert!(parse_slot_time(value).is_err(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn repeated_hour_resolves_to_daylight_time() {
+        // Daylight saving ends at 3:00, so 2:30 happens twice. The first is at +11:00.
+        let time = parse_slot_time("05/04/2026 2:30").unwrap();
+        assert_eq!(time.offset().fix().local_minus_utc(), 11 * 3600);
+    }
+
+    #[test]
+    fn slots_sort_by_time_not_by_text() {
+        let mut slots = vec![
+            slot("21/02/2026 8:00"),
+            slot("20/02/2026 10:50"),
+            slot("20/02/2026 9:05"),
+            slot("01/03/2026 7:30"),
+        ];
+        slots.sort();
+        let order: Vec<String> = slots.iter().map(TimeSlot::display).collect();
+        assert_eq!(
+            order,
+            [
+                "20/02/2026 9:05",
+                "20/02/2026 10:50",
+                "21/02/2026 8:00",
+                "01/03/2026 7:30"
+            ]
+        );
+    }
+
+    #[test]
+    fn start_time_round_trips_in_rta_format() {
+        let json = r#"{"availability":true,"slot_number":4,"startTime":"20/02/2026 9:05"}"#;
+        let slot: TimeSlot = serde_json::from_str(json).unwrap();
+        assert_eq!(slot.start_time, parse_slot_time("20/02/2026 9:05").unwrap());
+        assert_eq!(serde_json::to_string(&slot).unwrap(), json);
+
+        let invalid = r#"{"availability":true,"slot_number":4,"startTime":"20/02/2026"}"#;
+        assert!(serde_json::from_str::<TimeSlot>(invalid).is_err());
+    }
+
+    #[test]
+    fn drops_invalid_slots_from_a_location() {
+        let json = r#"{
+            "location": "21",
+            "slots": [
+                {"availability": true, "slot_number": 1, "startTime": "20/02/2026 9:05"},
+                {"availability": true, "slot_number": 2, "startTime": "not a time"},
+                {"availability": true, "slot_number": 3, "startTime": "20/02/2026 10:50"}
+            ],
+            "next_available_date": null
+        }"#;
+        let bookings: LocationBookings = serde_json::from_str(json).unwrap();
+        let numbers: Vec<_> = bookings.slots.iter().map(|s| s.slot_number).collect();
+        assert_eq!(numbers, [Some(1), Some(3)]);
+        assert_eq!(bookings.test_type, TestType::Car);
+    }
+}
@@ -0,0 +1,91 @@
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use super::circuit_breaker::CircuitBreaker;
+use super::proxy_pool::{ProxyPool, ProxyStats};
+use super::proxy_source;
+use super::runs::{LocationRunStats, RunLog, RunSummary};
+use super::schedule::next_planned_run;
+use crate::settings::{QuietHours, Settings};
+
+#[derive(Serialize)]
+struct ProxyStatus {
+    proxy: String,
+    quarantined: bool,
+    #[serde(flatten)]
+    stats: ProxyStats,
+}
+
+/// `GET /api/status/proxies`
+pub async fn proxy_status() -> Response {
+    let now = chrono::Utc::now();
+    let proxies: Vec<ProxyStatus> = ProxyPool::stats()
+        .into_iter()
+        .map(|(proxy, stats)| ProxyStatus {
+            proxy: proxy_source::mask(&proxy),
+            quarantined: stats.is_quarantined(now),
+            stats,
+        })
+        .collect();
+
+    Json(proxies).into_response()
+}
+
+#[derive(Serialize)]
+struct ScheduleStatus {
+    next_run: Option<chrono::DateTime<chrono::Utc>>,
+    scrape_schedule: Option<String>,
+    quiet_hours: Vec<QuietHours>,
+    jitter_min: u64,
+}
+
+/// `GET /api/status/schedule`
+pub async fn schedule_status(State(settings): State<Settings>) -> Response {
+    Json(ScheduleStatus {
+        next_run: next_planned_run(),
+        scrape_schedule: settings.scrape_schedule,
+        quiet_hours: settings.quiet_hours,
+        jitter_min: settings.schedule_jitter_min,
+    })
+    .into_response()
+}
+
+/// `GET /api/status/circuit`
+pub async fn circuit_status() -> Response {
+    Json(CircuitBreaker::status()).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct RunsQuery {
+    pub limit: Option<usize>,
+}
+
+/// `GET /api/status/runs?limit=20`, newest first.
+pub async fn runs_status(Query(query): Query<RunsQuery>) -> Response {
+    Json(RunLog::recent(query.limit.unwrap_or(20).min(500))).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct RunStatsQuery {
+    pub days: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct RunStats {
+    runs: Vec<RunSummary>,
+    locations: Vec<LocationRunStats>,
+}
+
+/// `GET /api/status/runs/stats?days=7`, with run times over the window and the centres that
+/// fail most.
+pub async fn run_stats(Query(query): Query<RunStatsQuery>) -> Response {
+    let since = chrono::Utc::now() - chrono::Duration::days(query.days.unwrap_or(7).clamp(1, 365));
+
+    Json(RunStats {
+        runs: RunLog::summaries_since(since),
+        locations: RunLog::location_stats_since(since),
+    })
+    .into_response()
+}
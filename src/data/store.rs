@@ -0,0 +1,562 @@
+use log::{debug, info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use super::shared_booking::{BookingData, LocationBookings, SlotLabel, TestType, TimeSlot};
+use crate::settings::{Settings, StorageBackend};
+
+/// Persistence for the current `BookingData`.
+pub trait BookingStore: Send + Sync {
+    /// Returns `None` when nothing has been stored yet.
+    fn load(&self) -> Result<Option<BookingData>, String>;
+
+    fn save(&self, data: &BookingData) -> Result<(), String>;
+}
+
+pub fn open_store(settings: &Settings) -> Result<Box<dyn BookingStore>, String> {
+    let path = settings.storage_path();
+    match settings.storage_backend {
+        StorageBackend::Json => Ok(Box::new(JsonFileStore::new(path))),
+        StorageBackend::Sqlite => Ok(Box::new(SqliteStore::open(path)?)),
+    }
+}
+
+pub struct JsonFileStore {
+    path: PathBuf,
+}
+
+impl JsonFileStore {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl BookingStore for JsonFileStore {
+    fn load(&self) -> Result<Option<BookingData>, String> {
+        if !self.path.exists() {
+            debug!("No path for booking data");
+            return Ok(None);
+        }
+
+        let json_str =
+            fs::read_to_string(&self.path).map_err(|e| format!("Failed to read file: {}", e))?;
+        serde_json::from_str::<BookingData>(&json_str)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse JSON: {}", e))
+    }
+
+    fn save(&self, data: &BookingData) -> Result<(), String> {
+        let json_str = serde_json::to_string_pretty(data)
+            .map_err(|e| format!("Failed to serialize data: {}", e))?;
+        write_atomic(&self.path, json_str.as_bytes())
+    }
+}
+
+/// Tells apart temp files of concurrent writes from this process.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A sibling of `path` to write to before renaming it over `path`. Unique per call, so
+/// concurrent writes to the same path don't interfere.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    PathBuf::from(tmp_name)
+}
+
+/// Writes to a sibling temp file and renames it over `path`, so readers never see a partial file.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let tmp_path = temp_path(path);
+
+    let result = File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .map_err(|e| format!("Failed to write to file: {}", e))
+        .and_then(|()| {
+            fs::rename(&tmp_path, path).map_err(|e| format!("Failed to replace file: {}", e))
+        });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Embedded SQLite store. Every save is recorded as a scrape run, with its own copy of the
+/// slots of the locations scraped since the last save, while `locations` always holds the
+/// latest state of each centre.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+const SQLITE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scrape_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    last_updated TEXT
+);
+CREATE TABLE IF NOT EXISTS locations (
+    id TEXT NOT NULL,
+    test_type TEXT NOT NULL DEFAULT 'car',
+    next_available_date TEXT,
+    last_scraped TEXT,
+    stale INTEGER NOT NULL DEFAULT 0,
+    run_id INTEGER NOT NULL REFERENCES scrape_runs(id),
+    PRIMARY KEY (id, test_type)
+);
+CREATE TABLE IF NOT EXISTS slots (
+    run_id INTEGER NOT NULL REFERENCES scrape_runs(id),
+    location_id TEXT NOT NULL,
+    test_type TEXT NOT NULL DEFAULT 'car',
+    start_time TEXT NOT NULL,
+    slot_number INTEGER,
+    availability INTEGER NOT NULL,
+    label TEXT,
+    PRIMARY KEY (run_id, location_id, test_type, start_time)
+);
+CREATE INDEX IF NOT EXISTS slots_by_location ON slots (location_id, test_type, start_time);
+";
+
+/// Bumped whenever `SQLITE_SCHEMA` changes in a way existing databases need migrating for.
+const SQLITE_SCHEMA_VERSION: i64 = 2;
+
+/// Version 0 databases may predate slot labels.
+const SQLITE_MIGRATE_LABELS: &str = "ALTER TABLE slots ADD COLUMN label TEXT;";
+
+/// Version 1 databases predate test types, so everything in them is for the car test.
+const SQLITE_MIGRATE_TEST_TYPES: &str = "
+ALTER TABLE locations RENAME TO locations_v0;
+ALTER TABLE slots RENAME TO slots_v0;
+DROP INDEX IF EXISTS slots_by_location;
+CREATE TABLE locations (
+    id TEXT NOT NULL,
+    test_type TEXT NOT NULL DEFAULT 'car',
+    next_available_date TEXT,
+    last_scraped TEXT,
+    stale INTEGER NOT NULL DEFAULT 0,
+    run_id INTEGER NOT NULL REFERENCES scrape_runs(id),
+    PRIMARY KEY (id, test_type)
+);
+CREATE TABLE slots (
+    run_id INTEGER NOT NULL REFERENCES scrape_runs(id),
+    location_id TEXT NOT NULL,
+    test_type TEXT NOT NULL DEFAULT 'car',
+    start_time TEXT NOT NULL,
+    slot_number INTEGER,
+    availability INTEGER NOT NULL,
+    label TEXT,
+    PRIMARY KEY (run_id, location_id, test_type, start_time)
+);
+INSERT INTO locations (id, next_available_date, last_scraped, stale, run_id)
+    SELECT id, next_available_date, last_scraped, stale, run_id FROM locations_v0;
+INSERT INTO slots (run_id, location_id, start_time, slot_number, availability, label)
+    SELECT run_id, location_id, start_time, slot_number, availability, label FROM slots_v0;
+DROP TABLE locations_v0;
+DROP TABLE slots_v0;
+";
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let mut conn =
+            Connection::open(path).map_err(|e| format!("Failed to open database: {}", e))?;
+        Self::migrate(&mut conn)?;
+        conn.execute_batch(SQLITE_SCHEMA)
+            .map_err(|e| format!("Failed to create schema: {}", e))?;
+        conn.pragma_update(None, "user_version", SQLITE_SCHEMA_VERSION)
+            .map_err(|e| format!("Failed to set schema version: {}", e))?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+        conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+            params![table, column],
+            |row| row.get(0),
+        )
+    }
+
+    /// Brings databases created by older versions up to date, one version at a time. A fresh
+    /// database has no tables yet and is left for `SQLITE_SCHEMA` to create.
+    fn migrate(conn: &mut Connection) -> Result<(), String> {
+        let db_err = |e: rusqlite::Error| format!("Failed to migrate database: {}", e);
+
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(db_err)?;
+        let has_locations: bool = conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'locations')",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(db_err)?;
+        if !has_locations {
+            return Ok(());
+        }
+
+        // Columns are checked as well, since databases from before versioning may already
+        // have some of them.
+        if version < 1 {
+            let tx = conn.transaction().map_err(db_err)?;
+            if !Self::has_column(&tx, "slots", "label").map_err(db_err)? {
+                info!("Migrating database to store slot labels");
+                tx.execute_batch(SQLITE_MIGRATE_LABELS).map_err(db_err)?;
+            }
+            tx.pragma_update(None, "user_version", 1).map_err(db_err)?;
+            tx.commit().map_err(db_err)?;
+        }
+
+        if version < 2 {
+            let tx = conn.transaction().map_err(db_err)?;
+            if !Self::has_column(&tx, "locations", "test_type").map_err(db_err)? {
+                info!("Migrating database to track test types");
+                tx.execute_batch(SQLITE_MIGRATE_TEST_TYPES)
+                    .map_err(db_err)?;
+            }
+            tx.pragma_update(None, "user_version", 2).map_err(db_err)?;
+            tx.commit().map_err(db_err)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl BookingStore for SqliteStore {
+    fn load(&self) -> Result<Option<BookingData>, String> {
+        let conn = self.conn.lock().unwrap();
+        let db_err = |e: rusqlite::Error| format!("Failed to load from database: {}", e);
+
+        let latest_run: Option<(i64, Option<String>)> = conn
+            .query_row(
+                "SELECT id, last_updated FROM scrape_runs ORDER BY id DESC LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(db_err)?;
+
+        let Some((_, last_updated)) = latest_run else {
+            debug!("No scrape runs in database");
+            return Ok(None);
+        };
+
+        let mut location_stmt = conn
+            .prepare(
+                "SELECT id, test_type, next_available_date, last_scraped, stale, run_id
+                 FROM locations ORDER BY id, test_type",
+            )
+            .map_err(db_err)?;
+        let mut slot_stmt = conn
+            .prepare(
+                "SELECT start_time, slot_number, availability, label FROM slots
+                 WHERE run_id = ?1 AND location_id = ?2 AND test_type = ?3 ORDER BY start_time",
+            )
+            .map_err(db_err)?;
+
+        let locations = location_stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, bool>(4)?,
+                    row.get::<_, i64>(5)?,
+                ))
+            })
+            .map_err(db_err)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_err)?;
+
+        let mut results = Vec::with_capacity(locations.len());
+        for (location, test_type, next_available_date, last_scraped, stale, run_id) in locations {
+            let Some(parsed_test_type) = TestType::parse(&test_type) else {
+                warn!(
+                    "Skipping location {} with unknown test type '{}'",
+                    location, test_type
+                );
+                continue;
+            };
+
+            let slots = slot_stmt
+                .query_map(params![run_id, location, test_type], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<u32>>(1)?,
+                        row.get::<_, bool>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                    ))
+                })
+                .map_err(db_err)?
+                .filter_map(|row| {
+                    let (start_time, slot_number, availability, label) = row.ok()?;
+                    let label = label.and_then(|label: String| SlotLabel::parse(&label));
+                    let start_time = chrono::DateTime::parse_from_rfc3339(&start_time).ok()?;
+                    Some(TimeSlot {
+                        availability,
+                        slot_number,
+                        start_time: start_time.with_timezone(&chrono_tz::Australia::Sydney),
+                        label,
+                    })
+                })
+                .collect();
+
+            results.push(LocationBookings {
+                location,
+                test_type: parsed_test_type,
+                slots,
+                next_available_date,
+                last_scraped,
+                stale,
+            });
+        }
+
+        Ok(Some(BookingData {
+            results,
+            last_updated,
+        }))
+    }
+
+    fn save(&self, data: &BookingData) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let db_err = |e: rusqlite::Error| format!("Failed to save to database: {}", e);
+
+        let tx = conn.transaction().map_err(db_err)?;
+
+        tx.execute(
+            "INSERT INTO scrape_runs (last_updated) VALUES (?1)",
+            params![data.last_updated],
+        )
+        .map_err(db_err)?;
+        let run_id = tx.last_insert_rowid();
+
+        {
+            let mut location_stmt = tx
+                .prepare(
+                    "INSERT INTO locations
+                        (id, test_type, next_available_date, last_scraped, stale, run_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT(id, test_type) DO UPDATE SET
+                        next_available_date = excluded.next_available_date,
+                        last_scraped = excluded.last_scraped,
+                        stale = excluded.stale,
+                        run_id = excluded.run_id",
+                )
+                .map_err(db_err)?;
+            let mut previous_stmt = tx
+                .prepare("SELECT last_scraped FROM locations WHERE id = ?1 AND test_type = ?2")
+                .map_err(db_err)?;
+            let mut stale_stmt = tx
+                .prepare("UPDATE locations SET stale = ?3 WHERE id = ?1 AND test_type = ?2")
+                .map_err(db_err)?;
+            let mut slot_stmt = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO slots
+                        (run_id, location_id, test_type, start_time, slot_number, availability, label)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )
+                .map_err(db_err)?;
+
+            for location in &data.results {
+                let previous: Option<Option<String>> = previous_stmt
+                    .query_row(
+                        params![location.location, location.test_type.as_str()],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(db_err)?;
+
+                // Not scraped since the last save, so its slots are already stored.
+                if previous.is_some_and(|last_scraped| last_scraped == location.last_scraped) {
+                    stale_stmt
+                        .execute(params![
+                            location.location,
+                            location.test_type.as_str(),
+                            location.stale,
+                        ])
+                        .map_err(db_err)?;
+                    continue;
+                }
+
+                location_stmt
+                    .execute(params![
+                        location.location,
+                        location.test_type.as_str(),
+                        location.next_available_date,
+                        location.last_scraped,
+                        location.stale,
+                        run_id,
+                    ])
+                    .map_err(db_err)?;
+
+                for slot in &location.slots {
+                    slot_stmt
+                        .execute(params![
+                            run_id,
+                            location.location,
+                            location.test_type.as_str(),
+                            slot.start_time.to_rfc3339(),
+                            slot.slot_number,
+                            slot.availability,
+                            slot.label.map(|label| label.as_str()),
+                        ])
+                        .map_err(db_err)?;
+                }
+            }
+        }
+
+        tx.commit().map_err(db_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::shared_booking::parse_slot_time;
+
+    fn location(id: &str, last_scraped: &str, stale: bool) -> LocationBookings {
+        LocationBookings {
+            location: id.to_string(),
+            test_type: TestType::Car,
+            slots: vec![TimeSlot {
+                availability: true,
+                slot_number: Some(1),
+                start_time: parse_slot_time("20/02/2026 10:50").unwrap(),
+                label: None,
+            }],
+            next_available_date: None,
+            last_scraped: Some(last_scraped.to_string()),
+            stale,
+        }
+    }
+
+    fn slot_rows(store: &SqliteStore) -> i64 {
+        let conn = store.conn.lock().unwrap();
+        conn.query_row("SELECT COUNT(*) FROM slots", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn save_only_writes_slots_of_scraped_locations() {
+        let store = SqliteStore::open(":memory:").unwrap();
+
+        store
+            .save(&BookingData {
+                results: vec![location("21", "t1", false), location("68", "t1", false)],
+                last_updated: Some("t1".into()),
+            })
+            .unwrap();
+        assert_eq!(slot_rows(&store), 2);
+
+        // 68 wasn't scraped again and is carried over as stale.
+        store
+            .save(&BookingData {
+                results: vec![location("21", "t2", false), location("68", "t1", true)],
+                last_updated: Some("t2".into()),
+            })
+            .unwrap();
+        assert_eq!(slot_rows(&store), 3);
+
+        let loaded = store.load().unwrap().unwrap();
+        let carried = loaded.location("68", TestType::Car).unwrap();
+        assert!(carried.stale);
+        assert_eq!(carried.slots.len(), 1);
+        assert_eq!(loaded.location("21", TestType::Car).unwrap().slots.len(), 1);
+    }
+
+    #[test]
+    fn migrates_databases_without_labels_or_test_types() {
+        let path = std::env::temp_dir().join(format!("store-migrate-{}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE scrape_runs (id INTEGER PRIMARY KEY AUTOINCREMENT, last_updated TEXT);
+                 CREATE TABLE locations (
+                    id TEXT PRIMARY KEY,
+                    next_available_date TEXT,
+                    last_scraped TEXT,
+                    stale INTEGER NOT NULL DEFAULT 0,
+                    run_id INTEGER NOT NULL
+                 );
+                 CREATE TABLE slots (
+                    run_id INTEGER NOT NULL,
+                    location_id TEXT NOT NULL,
+                    start_time TEXT NOT NULL,
+                    slot_number INTEGER,
+                    availability INTEGER NOT NULL,
+                    PRIMARY KEY (run_id, location_id, start_time)
+                 );
+                 INSERT INTO scrape_runs (last_updated) VALUES ('t1');
+                 INSERT INTO locations VALUES ('21', NULL, 't1', 0, 1);
+                 INSERT INTO slots VALUES (1, '21', '2026-02-20T10:50:00+11:00', 1, 1);",
+            )
+            .unwrap();
+        }
+
+        let store = SqliteStore::open(&path).unwrap();
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!(loaded.location("21", TestType::Car).unwrap().slots.len(), 1);
+
+        store
+            .save(&BookingData {
+                results: vec![location("21", "t2", false)],
+                last_updated: Some("t2".into()),
+            })
+            .unwrap();
+        let version: i64 = store
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SQLITE_SCHEMA_VERSION);
+
+        drop(store);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn concurrent_atomic_writes_use_their_own_temp_files() {
+        let dir = std::env::temp_dir().join(format!("write-atomic-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bookings.json");
+
+        let writers: Vec<_> = (0..8)
+            .map(|writer| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let contents = format!("writer {}", writer).repeat(10_000);
+                    for _ in 0..20 {
+                        write_atomic(&path, contents.as_bytes()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        // The file holds one whole write and no temp files are left behind.
+        let contents = fs::read_to_string(&path).unwrap();
+        let first = &contents[..8];
+        assert_eq!(contents, first.repeat(10_000));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
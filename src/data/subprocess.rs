@@ -0,0 +1,535 @@
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::time::Instant;
+
+use super::rta::SCRAPER_PY;
+use super::scraper::{
+    BlockedProxy, LocationFailure, ScrapeError, ScrapeFuture, ScrapeOutput, Scraper,
+};
+use super::shared_booking::TestType;
+use super::store::write_atomic;
+use crate::settings::Settings;
+
+/// Writes `scraper.py` to the temp directory for the child to run, rather than passing the
+/// whole script in its arguments. Rewritten for every child in case the file was cleaned up.
+fn script_path() -> Result<PathBuf, ScrapeError> {
+    let path = std::env::temp_dir().join(format!("nsw-scraper-{}.py", std::process::id()));
+    write_atomic(&path, SCRAPER_PY.as_bytes())
+        .map_err(|e| ScrapeError(format!("Failed to write scraper script: {}", e)))?;
+    Ok(path)
+}
+
+/// First line written to the child's stdin.
+#[derive(Debug, Serialize)]
+struct ScrapeRequest<'a> {
+    locations: &'a [String],
+    headless: bool,
+    username: &'a str,
+    password: &'a str,
+    have_booking: bool,
+    timeout_ms: u64,
+    proxies: &'a [String],
+    parallel_browsers: usize,
+    test_type: &'static str,
+    artifact_dir: Option<&'a str>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PartialResult {
+    /// Raw `timeslots` JSON per location.
+    #[serde(default)]
+    payloads: HashMap<String, String>,
+    #[serde(default)]
+    blocked_proxies: Vec<BlockedProxy>,
+    #[serde(default)]
+    failures: HashMap<String, LocationFailure>,
+    #[serde(default)]
+    durations_ms: HashMap<String, u64>,
+}
+
+/// One JSON line from the child's stdout.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ScraperEvent {
+    LocationStarted {
+        location: String,
+    },
+    LocationDone {
+        location: String,
+        payload: String,
+        duration_ms: u64,
+    },
+    LocationFailed {
+        location: String,
+        failure: LocationFailure,
+    },
+    /// A browser group finished, including groups that failed to log in or were blocked.
+    GroupDone {
+        #[serde(flatten)]
+        result: PartialResult,
+    },
+    Result {
+        #[serde(flatten)]
+        result: PartialResult,
+    },
+}
+
+/// How a single child process run ended.
+enum ChildExit {
+    Finished,
+    /// The child was killed by the watchdog, or exited without a result.
+    Stopped(String),
+}
+
+fn merge(output: &mut ScrapeOutput, result: PartialResult, test_type: TestType) {
+    output.durations_ms.extend(result.durations_ms);
+    for (location, payload) in result.payloads {
+        output.add_payload(&location, test_type, payload);
+    }
+    for (location, failure) in result.failures {
+        if !output.bookings.contains_key(&location) {
+            output.failures.insert(location, failure);
+        }
+    }
+    for blocked in result.blocked_proxies {
+        output.add_blocked(blocked);
+    }
+}
+
+/// Forwards the child's `LEVEL message` log lines to our logger.
+fn forward_log(line: &str) {
+    let (level, message) = line.split_once(' ').unwrap_or(("INFO", line));
+    match level {
+        "ERROR" | "CRITICAL" => error!(target: "rta_scraper", "{}", message),
+        "WARNING" => warn!(target: "rta_scraper", "{}", message),
+        "INFO" => info!(target: "rta_scraper", "{}", message),
+        "DEBUG" => debug!(target: "rta_scraper", "{}", message),
+        _ => info!(target: "rta_scraper", "{}", line),
+    }
+}
+
+/// Kills the child and, on Unix, the browsers it started, which share its process group.
+/// Must be called before the child is reaped, while its pid still names the group.
+async fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: only sends a signal, to the group the child was started as the leader of.
+        if unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) } != 0 {
+            let e = std::io::Error::last_os_error();
+            if e.raw_os_error() != Some(libc::ESRCH) {
+                error!("Failed to kill scraper process group: {}", e);
+            }
+        }
+    }
+
+    if let Err(e) = child.kill().await {
+        error!("Failed to kill scraper process: {}", e);
+    }
+}
+
+/// Runs `scraper.py` in a child process per scrape, so a hung browser or a crashed
+/// interpreter can't take the server down with it. A watchdog kills the child when it goes
+/// `location_timeout_secs` without reporting progress or the run passes
+/// `scrape_timeout_min`; locations it hadn't finished get another child, up to
+/// `scraper_restarts` times.
+pub struct SubprocessScraper;
+
+impl SubprocessScraper {
+    async fn run_child(
+        settings: &Settings,
+        locations: &[String],
+        test_type: TestType,
+        proxies: &[String],
+        artifact_dir: Option<&str>,
+        deadline: Instant,
+        output: &mut ScrapeOutput,
+    ) -> Result<ChildExit, ScrapeError> {
+        let script = script_path()?;
+        let mut command = Command::new(&settings.python_path);
+        command
+            .arg("-u")
+            .arg(&script)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // Chromium runs as the child's children. Its own process group lets `kill` reach them.
+        #[cfg(unix)]
+        command.process_group(0);
+        let mut child = command.spawn().map_err(|e| {
+            ScrapeError(format!(
+                "Failed to start scraper with '{}': {}",
+                settings.python_path, e
+            ))
+        })?;
+
+        let request = ScrapeRequest {
+            locations,
+            headless: settings.headless,
+            username: &settings.username,
+            password: &settings.password,
+            have_booking: settings.have_booking,
+            timeout_ms: settings.element_timeout,
+            proxies,
+            parallel_browsers: settings.parallel_browsers,
+            test_type: test_type.as_str(),
+            artifact_dir,
+        };
+        let mut line = serde_json::to_string(&request)
+            .map_err(|e| ScrapeError(format!("Failed to serialize scrape request: {}", e)))?;
+        line.push('\n');
+
+        let mut stdin = child.stdin.take().expect("child stdin is piped");
+        if let Err(e) = stdin.write_all(line.as_bytes()).await {
+            kill(&mut child).await;
+            return Err(ScrapeError(format!("Failed to send scrape request: {}", e)));
+        }
+        drop(stdin);
+
+        let stderr = child.stderr.take().expect("child stderr is piped");
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                forward_log(&line);
+            }
+        });
+
+        let stdout = child.stdout.take().expect("child stdout is piped");
+        let mut lines = BufReader::new(stdout).lines();
+        let location_timeout = Duration::from_secs(settings.location_timeout_secs);
+        let mut current_location: Option<String> = None;
+
+        let exit = loop {
+            let wait = location_timeout.min(deadline.saturating_duration_since(Instant::now()));
+
+            let line = match tokio::time::timeout(wait, lines.next_line()).await {
+                Ok(Ok(Some(line))) => line,
+                // The child is gone or unreadable, but its browsers may still be running.
+                Ok(Ok(None)) => {
+                    kill(&mut child).await;
+                    break ChildExit::Stopped("scraper exited without a result".into());
+                }
+                Ok(Err(e)) => {
+                    kill(&mut child).await;
+                    break ChildExit::Stopped(format!("failed to read scraper output: {}", e));
+                }
+                Err(_) if Instant::now() >= deadline => {
+                    kill(&mut child).await;
+                    break ChildExit::Stopped(format!(
+                        "scrape exceeded {} minutes",
+                        settings.scrape_timeout_min
+                    ));
+                }
+                Err(_) => {
+                    kill(&mut child).await;
+                    break ChildExit::Stopped(format!(
+                        "no progress for {}s{}",
+                        settings.location_timeout_secs,
+                        current_location
+                            .as_deref()
+                            .map(|location| format!(" while scraping location {}", location))
+                            .unwrap_or_default()
+                    ));
+                }
+            };
+
+            let event = match serde_json::from_str::<ScraperEvent>(&line) {
+                Ok(event) => event,
+                Err(_) => {
+                    // Browser libraries occasionally print to stdout as well.
+                    debug!("Ignoring scraper output: {}", line);
+                    continue;
+                }
+            };
+
+            match event {
+                ScraperEvent::LocationStarted { location } => current_location = Some(location),
+                ScraperEvent::LocationDone {
+                    location,
+                    payload,
+                    duration_ms,
+                } => merge(
+                    output,
+                    PartialResult {
+                        payloads: HashMap::from([(location.clone(), payload)]),
+                        durations_ms: HashMap::from([(location, duration_ms)]),
+                        ..Default::default()
+                    },
+                    test_type,
+                ),
+                ScraperEvent::LocationFailed { location, failure } => merge(
+                    output,
+                    PartialResult {
+                        failures: HashMap::from([(location, failure)]),
+                        ..Default::default()
+                    },
+                    test_type,
+                ),
+                ScraperEvent::GroupDone { result } => merge(output, result, test_type),
+                ScraperEvent::Result { result } => {
+                    merge(output, result, test_type);
+                    break ChildExit::Finished;
+                }
+            }
+        };
+
+        match child.wait().await {
+            Ok(status) if !status.success() => {
+                warn!("Scraper process exited with {}", status);
+            }
+            Err(e) => error!("Failed to wait for scraper process: {}", e),
+            _ => {}
+        }
+
+        Ok(exit)
+    }
+
+    async fn scrape_supervised(
+        settings: &Settings,
+        locations: Vec<String>,
+        test_type: TestType,
+        proxies: Vec<String>,
+        artifact_dir: Option<String>,
+    ) -> Result<ScrapeOutput, ScrapeError> {
+        let deadline = Instant::now() + Duration::from_secs(settings.scrape_timeout_min * 60);
+        let mut output = ScrapeOutput::default();
+        let mut pending = locations;
+        let mut last_reason = String::new();
+
+        for run in 0..=settings.scraper_restarts {
+            if run > 0 {
+                warn!(
+                    "Restarting scraper process for {} unfinished locations",
+                    pending.len()
+                );
+            }
+
+            let reason = match Self::run_child(
+                settings,
+                &pending,
+                test_type,
+                &proxies,
+                artifact_dir.as_deref(),
+                deadline,
+                &mut output,
+            )
+            .await?
+            {
+                ChildExit::Finished => return Ok(output),
+                ChildExit::Stopped(reason) => reason,
+            };
+
+            error!("Scraper process stopped: {}", reason);
+            last_reason = reason;
+
+            pending.retain(|location| {
+                !output.bookings.contains_key(location) && !output.failures.contains_key(location)
+            });
+
+            if pending.is_empty() || Instant::now() >= deadline {
+                break;
+            }
+        }
+
+        if output.bookings.is_empty() && output.failures.is_empty() {
+            return Err(ScrapeError(last_reason));
+        }
+        Ok(output)
+    }
+}
+
+impl Scraper for SubprocessScraper {
+    fn name(&self) -> &'static str {
+        "subprocess"
+    }
+
+    fn scrape<'a>(
+        &'a self,
+        locations: Vec<String>,
+        test_type: TestType,
+        settings: &'a Settings,
+        proxies: Vec<String>,
+        artifact_dir: Option<String>,
+    ) -> ScrapeFuture<'a> {
+        Box::pin(async move {
+            info!(
+                "Starting {} scrape in a subprocess with {} parallel browsers for {} locations",
+                test_type.as_str(),
+                settings.parallel_browsers,
+                locations.len()
+            );
+
+            let output =
+                Self::scrape_supervised(settings, locations, test_type, proxies, artifact_dir)
+                    .await?;
+
+            info!(
+                "Scraping complete: {} locations scraped, {} failed.",
+                output.bookings.len(),
+                output.failures.len()
+            );
+            Ok(output)
+        })
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    const PAYLOAD: &str =
+        r#"{\"ajaxresult\":{\"slots\":{\"nextAvailableDate\":null,\"listTimeSlot\":[]}}}"#;
+
+    /// Writes a shell script standing in for Python, which ignores the scraper script it is
+    /// given and runs `body` instead. Each run's request is saved as `request-<n>`.
+    fn fake_python(dir: &Path, body: &str) -> String {
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join("python");
+        let script = format!(
+            "#!/bin/sh\ncd '{}'\nrun=$(ls | grep -c '^request-')\nhead -n 1 > request-$run\n{}\n",
+            dir.display(),
+            body
+        );
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn location_done(location: &str) -> String {
+        format!(
+            r#"echo '{{"type":"location_done","location":"{}","payload":"{}","duration_ms":5}}'"#,
+            location, PAYLOAD
+        )
+    }
+
+    fn settings(python_path: &str, restarts: u32) -> Settings {
+        serde_yaml::from_str(&format!(
+            "headless: true
+username: ''
+password: ''
+have_booking: false
+element_timeout: 1000
+retries: 1
+scrape_refresh_time_min: 240
+proxy_path: ''
+parallel_browsers: 1
+scraping_enabled: true
+python_path: '{}'
+location_timeout_secs: 1
+scrape_timeout_min: 1
+scraper_restarts: {}",
+            python_path, restarts
+        ))
+        .unwrap()
+    }
+
+    fn requested_locations(dir: &Path, run: usize) -> Vec<String> {
+        let request = fs::read_to_string(dir.join(format!("request-{}", run))).unwrap();
+        let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+        serde_json::from_value(request["locations"].clone()).unwrap()
+    }
+
+    /// Whether `pid` is gone or only waiting to be reaped.
+    fn is_dead(pid: &str) -> bool {
+        match fs::read_to_string(format!("/proc/{}/stat", pid.trim())) {
+            Ok(stat) => stat
+                .rsplit(')')
+                .next()
+                .unwrap()
+                .trim_start()
+                .starts_with('Z'),
+            Err(_) => true,
+        }
+    }
+
+    #[tokio::test]
+    async fn watchdog_kills_the_scraper_and_its_browsers() {
+        let dir = std::env::temp_dir().join(format!("subprocess-watchdog-{}", std::process::id()));
+        // The background sleep stands in for a browser the scraper started.
+        let python = fake_python(
+            &dir,
+            r#"sleep 300 &
+echo $! > browser.pid
+echo '{"type":"location_started","location":"21"}'
+sleep 300"#,
+        );
+        let settings = settings(&python, 0);
+
+        let locations = vec!["21".to_string()];
+        let result =
+            SubprocessScraper::scrape_supervised(&settings, locations, TestType::Car, vec![], None)
+                .await;
+
+        let error = result.unwrap_err();
+        assert_eq!(error.0, "no progress for 1s while scraping location 21");
+        let browser = fs::read_to_string(dir.join("browser.pid")).unwrap();
+        assert!(
+            is_dead(&browser),
+            "browser {} outlived the scraper",
+            browser
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn restarts_for_the_locations_left_unfinished() {
+        let dir = std::env::temp_dir().join(format!("subprocess-restart-{}", std::process::id()));
+        let python = fake_python(
+            &dir,
+            &format!(
+                r#"if [ "$run" = 0 ]; then
+{}
+echo '{{"type":"location_started","location":"68"}}'
+exit 1
+fi
+{}
+echo '{{"type":"result"}}'"#,
+                location_done("21"),
+                location_done("68")
+            ),
+        );
+        let settings = settings(&python, 1);
+
+        let locations = vec!["21".to_string(), "68".to_string()];
+        let output =
+            SubprocessScraper::scrape_supervised(&settings, locations, TestType::Car, vec![], None)
+                .await
+                .unwrap();
+
+        assert!(output.bookings.contains_key("21"));
+        assert!(output.bookings.contains_key("68"));
+        assert_eq!(requested_locations(&dir, 0), ["21", "68"]);
+        assert_eq!(requested_locations(&dir, 1), ["68"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_restart_limit() {
+        let dir = std::env::temp_dir().join(format!("subprocess-limit-{}", std::process::id()));
+        let python = fake_python(&dir, "exit 1");
+        let settings = settings(&python, 2);
+
+        let locations = vec!["21".to_string()];
+        let result =
+            SubprocessScraper::scrape_supervised(&settings, locations, TestType::Car, vec![], None)
+                .await;
+
+        assert_eq!(result.unwrap_err().0, "scraper exited without a result");
+        assert!(dir.join("request-2").exists());
+        assert!(!dir.join("request-3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use log::warn;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+use super::shared_booking::{parse_slot_time, LocationBookings, TestType, TimeSlot};
+
+/// The `timeslots` object RTA's location page sets. Fields we don't read end up in `extra`,
+/// so format changes show up in `unknown_fields` instead of being silently dropped.
+#[derive(Debug, Default, Deserialize)]
+pub struct Timeslots {
+    #[serde(default)]
+    pub ajaxresult: Option<AjaxResult>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AjaxResult {
+    #[serde(default)]
+    pub slots: Option<SlotList>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotList {
+    #[serde(default)]
+    pub next_available_date: Option<String>,
+    #[serde(default)]
+    pub list_time_slot: Vec<RawTimeSlot>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawTimeSlot {
+    #[serde(default)]
+    pub availability: bool,
+    #[serde(default)]
+    pub slot_number: Option<u32>,
+    #[serde(default)]
+    pub start_time: String,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl Timeslots {
+    /// Paths of fields in the payload that none of the structs above read, such as
+    /// `ajaxresult.slots.listTimeSlot[].duration`.
+    pub fn unknown_fields(&self) -> BTreeSet<String> {
+        let mut fields: BTreeSet<String> = self.extra.keys().cloned().collect();
+
+        if let Some(ajax) = &self.ajaxresult {
+            fields.extend(ajax.extra.keys().map(|key| format!("ajaxresult.{}", key)));
+
+            if let Some(slots) = &ajax.slots {
+                fields.extend(
+                    slots
+                        .extra
+                        .keys()
+                        .map(|key| format!("ajaxresult.slots.{}", key)),
+                );
+                fields.extend(slots.list_time_slot.iter().flat_map(|slot| {
+                    slot.extra
+                        .keys()
+                        .map(|key| format!("ajaxresult.slots.listTimeSlot[].{}", key))
+                }));
+            }
+        }
+
+        fields
+    }
+
+    /// Converts the payload into `LocationBookings`. Slots with an unreadable start time are
+    /// dropped.
+    pub fn into_bookings(self, location: &str, test_type: TestType) -> LocationBookings {
+        let slot_list = self
+            .ajaxresult
+            .and_then(|ajax| ajax.slots)
+            .unwrap_or_default();
+
+        let slots = slot_list
+            .list_time_slot
+            .into_iter()
+            .filter_map(|raw| match parse_slot_time(&raw.start_time) {
+                Ok(start_time) => Some(TimeSlot {
+                    availability: raw.availability,
+                    slot_number: raw.slot_number,
+                    start_time,
+                    label: None,
+                }),
+                Err(e) => {
+                    warn!(
+                        "Dropping invalid time slot for location {}: {}",
+                        location, e
+                    );
+                    None
+                }
+            })
+            .collect();
+
+        LocationBookings {
+            location: location.to_string(),
+            test_type,
+            slots,
+            next_available_date: slot_list.next_available_date,
+            last_scraped: None,
+            stale: false,
+        }
+    }
+}
+
+pub fn parse_timeslots(json: &str) -> Result<Timeslots, String> {
+    serde_json::from_str(json).map_err(|e| format!("Invalid timeslots payload: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_fields_the_parser_does_not_read() {
+        let timeslots = parse_timeslots(
+            r#"{
+                "ajaxresult": {
+                    "slots": {
+                        "nextAvailableDate": null,
+                        "listTimeSlot": [
+                            {"availability": true, "startTime": "11/03/2026 14:15", "duration": 45}
+                        ],
+                        "centreName": "Parramatta"
+                    },
+                    "status": "ok"
+                },
+                "version": 2
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            timeslots.unknown_fields().into_iter().collect::<Vec<_>>(),
+            vec![
+                "ajaxresult.slots.centreName",
+                "ajaxresult.slots.listTimeSlot[].duration",
+                "ajaxresult.status",
+                "version",
+            ]
+        );
+        assert!(parse_timeslots("{}").unwrap().unknown_fields().is_empty());
+    }
+}
@@ -0,0 +1,256 @@
+use log::{error, info, warn};
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::booking::BookingManager;
+use super::jobs::{JobResult, ScrapeJob, WorkerRequest};
+use super::proxy_source;
+use super::scraper::{create_scraper, FailureCategory, LocationFailure, ScrapeOutput, Scraper};
+use crate::settings::{ScraperBackend, Settings};
+
+/// Client side of the job queue: leases jobs from `primary_url`, scrapes them with this
+/// host's own backend and proxies, and posts the results back.
+struct Worker {
+    client: Client,
+    primary_url: String,
+    token: String,
+    name: String,
+}
+
+impl Worker {
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.primary_url.trim_end_matches('/'), path)
+    }
+
+    fn request(&self) -> WorkerRequest {
+        WorkerRequest {
+            worker: self.name.clone(),
+        }
+    }
+
+    async fn lease(&self) -> Result<Option<ScrapeJob>, String> {
+        let response = self
+            .client
+            .post(self.url("/api/worker/lease"))
+            .bearer_auth(&self.token)
+            .json(&self.request())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach primary: {}", e))?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(None),
+            status if status.is_success() => response
+                .json()
+                .await
+                .map(Some)
+                .map_err(|e| format!("Invalid job from primary: {}", e)),
+            status => Err(format!("Primary refused lease: {}", status)),
+        }
+    }
+
+    /// Returns false once the lease is lost to another worker.
+    async fn renew(&self, id: u64) -> Result<bool, String> {
+        let response = self
+            .client
+            .post(self.url(&format!("/api/worker/jobs/{}/renew", id)))
+            .bearer_auth(&self.token)
+            .json(&self.request())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach primary: {}", e))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            StatusCode::CONFLICT => Ok(false),
+            status => Err(format!("Primary refused renewal: {}", status)),
+        }
+    }
+
+    async fn release(&self, id: u64) -> Result<(), String> {
+        let response = self
+            .client
+            .post(self.url(&format!("/api/worker/jobs/{}/release", id)))
+            .bearer_auth(&self.token)
+            .json(&self.request())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach primary: {}", e))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("Primary refused release: {}", response.status()))
+        }
+    }
+
+    /// Proxy credentials stay on the worker; the primary only sees them masked.
+    async fn complete(&self, id: u64, mut output: ScrapeOutput) -> Result<(), String> {
+        for blocked in &mut output.blocked_proxies {
+            blocked.proxy = proxy_source::mask(&blocked.proxy);
+        }
+
+        let response = self
+            .client
+            .post(self.url(&format!("/api/worker/jobs/{}/result", id)))
+            .bearer_auth(&self.token)
+            .json(&JobResult {
+                worker: self.name.clone(),
+                output,
+            })
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach primary: {}", e))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::GONE => {
+                warn!("Job {} was finished by another worker", id);
+                Ok(())
+            }
+            StatusCode::CONFLICT => {
+                warn!("Job {} was leased to another worker", id);
+                Ok(())
+            }
+            status => Err(format!("Primary refused result: {}", status)),
+        }
+    }
+
+    async fn run_job(&self, job: ScrapeJob, scraper: &dyn Scraper, settings: &Settings) {
+        info!(
+            "Leased job {}: {} {} locations",
+            job.id,
+            job.locations.len(),
+            job.test_type.as_str()
+        );
+
+        let proxies = if scraper.requires_proxies() {
+            match BookingManager::select_proxies(settings) {
+                Ok(proxies) => {
+                    info!("Using proxies: {:?}", proxy_source::mask_all(&proxies));
+                    proxies
+                }
+                Err(e) => {
+                    // Hand the job back so a worker with proxies picks it up.
+                    error!("{}", e);
+                    if let Err(e) = self.release(job.id).await {
+                        error!("Failed to release job {}: {}", job.id, e);
+                    }
+                    return;
+                }
+            }
+        } else {
+            Vec::new()
+        };
+
+        let renew_every = Duration::from_secs((job.lease_secs / 3).max(1));
+        let scrape = scraper.scrape(
+            job.locations.clone(),
+            job.test_type,
+            settings,
+            proxies.clone(),
+            None,
+        );
+        tokio::pin!(scrape);
+
+        let started = Instant::now();
+        let result = loop {
+            tokio::select! {
+                result = &mut scrape => break result,
+                _ = tokio::time::sleep(renew_every) => {
+                    match self.renew(job.id).await {
+                        Ok(true) => {}
+                        // Another worker has the job now, so its result would be turned away.
+                        Ok(false) => {
+                            error!("Lost the lease on job {}, abandoning it", job.id);
+                            return;
+                        }
+                        Err(e) => warn!("Failed to renew lease on job {}: {}", job.id, e),
+                    }
+                }
+            }
+        };
+
+        let output = match result {
+            Ok(output) => {
+                BookingManager::record_proxy_outcomes(&proxies, &output, started.elapsed());
+                output
+            }
+            Err(e) => {
+                error!("Scraping job {} failed: {}", job.id, e);
+                let duration_ms = started.elapsed().as_millis() as u64;
+                ScrapeOutput {
+                    failures: job
+                        .locations
+                        .iter()
+                        .map(|location| {
+                            (
+                                location.clone(),
+                                LocationFailure {
+                                    category: FailureCategory::NavigationError,
+                                    message: e.to_string(),
+                                    duration_ms: Some(duration_ms),
+                                    artifacts: None,
+                                },
+                            )
+                        })
+                        .collect::<HashMap<_, _>>(),
+                    ..Default::default()
+                }
+            }
+        };
+
+        if let Err(e) = self.complete(job.id, output).await {
+            error!("Failed to post result of job {}: {}", job.id, e);
+        }
+    }
+}
+
+/// Runs this process as a worker of `primary_url` until it is stopped.
+pub async fn run_worker(settings: Settings) -> Result<(), String> {
+    if settings.scraper_backend == ScraperBackend::Workers {
+        return Err("Worker mode needs a backend that scrapes itself, not workers".into());
+    }
+    let primary_url = settings
+        .primary_url
+        .clone()
+        .ok_or("Worker mode needs primary_url")?;
+    let token = settings
+        .worker_token
+        .clone()
+        .ok_or("Worker mode needs worker_token")?;
+    let name = settings.worker_name.clone().unwrap_or_else(|| {
+        std::env::var("HOSTNAME").unwrap_or_else(|_| format!("worker-{}", std::process::id()))
+    });
+
+    let worker = Worker {
+        client: Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))?,
+        primary_url,
+        token,
+        name,
+    };
+    let scraper = create_scraper(&settings);
+    let poll = Duration::from_secs(settings.worker_poll_secs);
+
+    info!(
+        "Worker {} polling {} with the {} backend",
+        worker.name,
+        worker.primary_url,
+        scraper.name()
+    );
+
+    loop {
+        match worker.lease().await {
+            Ok(Some(job)) => worker.run_job(job, scraper.as_ref(), &settings).await,
+            Ok(None) => tokio::time::sleep(poll).await,
+            Err(e) => {
+                warn!("{}", e);
+                tokio::time::sleep(poll).await;
+            }
+        }
+    }
+}
@@ -2,13 +2,33 @@
 use std::fs::File;
 use std::io::Read;
 
-use axum::Router;
+use axum::routing::{get, post};
+use axum::{middleware, Router};
 use leptos::prelude::*;
 use leptos_axum::{generate_route_list, LeptosRoutes};
 use nsw_closest_display_lib::app::{shell, App};
+use nsw_closest_display_lib::data::auth::require_bearer_token;
 use nsw_closest_display_lib::data::booking::BookingManager;
+use nsw_closest_display_lib::data::calendar::{location_calendar, locations_calendar};
+use nsw_closest_display_lib::data::export::{export_csv, export_json};
+use nsw_closest_display_lib::data::feed::atom_feed;
+use nsw_closest_display_lib::data::history::{
+    location_history, location_slots_as_of, HistoryManager,
+};
+use nsw_closest_display_lib::data::jobs::{
+    complete_job, lease_job, release_job, renew_job, worker_status,
+};
 use nsw_closest_display_lib::data::location::Location;
-use nsw_closest_display_lib::settings::Settings;
+use nsw_closest_display_lib::data::payloads::reprocess_payloads;
+use nsw_closest_display_lib::data::proxy_pool::ProxyPool;
+use nsw_closest_display_lib::data::proxy_source::ProxySource;
+use nsw_closest_display_lib::data::runs::RunLog;
+use nsw_closest_display_lib::data::status::{
+    circuit_status, proxy_status, run_stats, runs_status, schedule_status,
+};
+use nsw_closest_display_lib::data::store::open_store;
+use nsw_closest_display_lib::data::worker::run_worker;
+use nsw_closest_display_lib::settings::{ScraperBackend, Settings};
 
 // FIX: HACKY
 fn get_location_names() -> Vec<String> {
@@ -37,27 +57,129 @@ async fn main() {
     let addr = leptos_options.site_addr;
     let routes = generate_route_list(App);
 
-    let data_file_path = "data/bookings.json";
-    match BookingManager::init_from_file(data_file_path) {
-        Ok(_) => println!("BookingManager initialized from file"),
-        Err(e) => println!("Failed to initialize BookingManager from file: {}", e),
+    let settings = Settings::from_yaml("settings.yaml").unwrap();
+
+    // `reprocess-payloads [output]` rebuilds history from the payload archive and exits.
+    if std::env::args().nth(1).as_deref() == Some("reprocess-payloads") {
+        let output = std::env::args()
+            .nth(2)
+            .unwrap_or_else(|| "data/history.reprocessed.jsonl".to_string());
+        let Some(archive_path) = &settings.payload_archive_path else {
+            println!("payload_archive_path is not set");
+            return;
+        };
+        match reprocess_payloads(archive_path, &output) {
+            Ok(summary) => {
+                println!(
+                    "Wrote {} runs from {} payloads to {} ({} invalid)",
+                    summary.runs, summary.payloads, output, summary.invalid
+                );
+                if !summary.unknown_fields.is_empty() {
+                    println!("Unknown fields: {:?}", summary.unknown_fields);
+                }
+            }
+            Err(e) => println!("Failed to reprocess payloads: {}", e),
+        }
+        return;
     }
 
-    let settings = Settings::from_yaml("settings.yaml").unwrap();
+    // `worker` leases scrape jobs from `primary_url` instead of serving the site.
+    if std::env::args().nth(1).as_deref() == Some("worker") {
+        if let Err(e) = ProxyPool::init_from_file(&settings.proxy_state_path) {
+            println!("Failed to initialize ProxyPool from file: {}", e);
+        }
+        ProxySource::start_remote_refresh(&settings);
+
+        if let Err(e) = run_worker(settings).await {
+            println!("Worker stopped: {}", e);
+        }
+        return;
+    }
+
+    if settings.scraper_backend == ScraperBackend::Demo {
+        if let Err(e) = std::fs::create_dir_all(&settings.demo_data_dir) {
+            println!("Failed to create {}: {}", settings.demo_data_dir, e);
+        }
+    }
+
+    match open_store(&settings).and_then(BookingManager::init_from_store) {
+        Ok(_) => println!("BookingManager initialized from {}", settings.storage_path()),
+        Err(e) => println!("Failed to initialize BookingManager from store: {}", e),
+    }
+
+    match HistoryManager::init_from_file(&settings.history_path) {
+        Ok(_) => println!("HistoryManager initialized from file"),
+        Err(e) => println!("Failed to initialize HistoryManager from file: {}", e),
+    }
+
+    match RunLog::init_from_file(&settings.runs_path) {
+        Ok(_) => println!("RunLog initialized from file"),
+        Err(e) => println!("Failed to initialize RunLog from file: {}", e),
+    }
+
+    match ProxyPool::init_from_file(&settings.proxy_state_path) {
+        Ok(_) => println!("ProxyPool initialized from file"),
+        Err(e) => println!("Failed to initialize ProxyPool from file: {}", e),
+    }
+
+    ProxySource::start_remote_refresh(&settings);
 
     let location_id = get_location_names();
+    let site_url = settings.site_url.clone();
+    // Server functions read the configuration from the context.
+    let context_settings = settings.clone();
+
+    let status_routes = Router::new()
+        .route("/api/status/proxies", get(proxy_status))
+        .route("/api/status/circuit", get(circuit_status))
+        .route("/api/status/runs", get(runs_status))
+        .route("/api/status/runs/stats", get(run_stats))
+        .route("/api/status/schedule", get(schedule_status))
+        .route("/api/status/workers", get(worker_status))
+        .route_layer(middleware::from_fn_with_state(
+            settings.admin_token.clone(),
+            require_bearer_token,
+        ))
+        .with_state(settings.clone());
 
-    if settings.scraping_enabled {
-        BookingManager::start_background_updates(location_id, data_file_path.to_string(), settings);
+    let worker_routes = Router::new()
+        .route("/api/worker/lease", post(lease_job))
+        .route("/api/worker/jobs/{id}/renew", post(renew_job))
+        .route("/api/worker/jobs/{id}/release", post(release_job))
+        .route("/api/worker/jobs/{id}/result", post(complete_job))
+        .route_layer(middleware::from_fn_with_state(
+            settings.worker_token.clone(),
+            require_bearer_token,
+        ));
+
+    if settings.scraping_enabled || settings.scraper_backend == ScraperBackend::Demo {
+        BookingManager::start_background_updates(location_id, settings);
     } else {
         println!("Scraping is disabled. Running in UI-only mode.");
     }
 
     let app = Router::new()
-        .leptos_routes(&leptos_options, routes, {
-            let leptos_options = leptos_options.clone();
-            move || shell(leptos_options.clone())
-        })
+        .route("/api/export.csv", get(export_csv))
+        .route("/api/export.json", get(export_json))
+        .route("/api/calendar.ics", get(locations_calendar))
+        .route("/api/calendar/{file}", get(location_calendar))
+        .route(
+            "/api/feed.atom",
+            get(move |query| atom_feed(query, site_url.clone())),
+        )
+        .route("/api/history/{location}", get(location_history))
+        .route("/api/history/{location}/slots", get(location_slots_as_of))
+        .merge(status_routes)
+        .merge(worker_routes)
+        .leptos_routes_with_context(
+            &leptos_options,
+            routes,
+            move || provide_context(context_settings.clone()),
+            {
+                let leptos_options = leptos_options.clone();
+                move || shell(leptos_options.clone())
+            },
+        )
         .fallback(leptos_axum::file_and_error_handler(shell))
         .with_state(leptos_options);
 
@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 use web_sys::wasm_bindgen::prelude::Closure;
 
 use crate::data::location::LocationManager;
-use crate::data::shared_booking::TimeSlot;
+use crate::data::shared_booking::{SlotLabel, TestType, TimeSlot};
 use crate::pages::location_table::LocationsTable;
 use crate::utils::date::TimeDisplay;
 use crate::utils::geocoding::geocode_address;
@@ -17,6 +17,10 @@ use crate::utils::geocoding::geocode_address;
 pub struct LocationBookingViewModel {
     pub location: String,
     pub earliest_slot: Option<TimeSlot>,
+    pub last_scraped: Option<String>,
+    pub stale: bool,
+    /// Available slots labelled as likely cancellations in the latest scrape.
+    pub likely_cancellations: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,9 +53,18 @@ pub async fn get_announcement() -> Result<Option<String>, ServerFnError> {
     }
 }
 
+/// The test types this site scrapes, so the selector only offers ones that can have data.
+#[server(name = GetTestTypes, endpoint = "internaldGVzdHR5cGVz")]
+pub async fn get_test_types() -> Result<Vec<TestType>, ServerFnError> {
+    use crate::settings::Settings;
+
+    Ok(expect_context::<Settings>().test_types)
+}
+
 #[server(name = GetBookings, endpoint = "internalbWF5b2Zmbm9vbmdy")]
 pub async fn get_location_bookings(
     client_etag: String,
+    test_type: TestType,
 ) -> Result<Option<BookingResponse>, ServerFnError> {
     use crate::data::booking::BookingManager;
     use axum::http::HeaderValue;
@@ -69,17 +82,27 @@ pub async fn get_location_bookings(
     let view_models: Vec<_> = booking_data
         .results
         .iter()
+        .filter(|location_booking| location_booking.test_type == test_type)
         .map(|location_booking| {
             let earliest_slot = location_booking
                 .slots
                 .iter()
                 .filter(|slot| slot.availability)
-                .min_by(|a, b| a.start_time.cmp(&b.start_time))
+                .min()
                 .cloned();
 
             LocationBookingViewModel {
                 location: location_booking.location.clone(),
                 earliest_slot,
+                last_scraped: location_booking.last_scraped.clone(),
+                stale: location_booking.stale,
+                likely_cancellations: location_booking
+                    .slots
+                    .iter()
+                    .filter(|slot| {
+                        slot.availability && slot.label == Some(SlotLabel::LikelyCancellation)
+                    })
+                    .count(),
             }
         })
         .collect();
@@ -94,13 +117,15 @@ pub async fn get_location_bookings(
 #[server(name = GetLocationDetails, endpoint = "internalc2hlbHRlcmVuZHF1")]
 pub async fn get_location_details(
     location_id: String,
+    test_type: TestType,
     client_etag: String,
 ) -> Result<Option<LocationDetailBookingResponse>, ServerFnError> {
     use crate::data::booking::BookingManager;
 
-    let (location_booking, server_etag) = BookingManager::get_location_data(location_id).ok_or(
-        ServerFnError::<NoCustomError>::ServerError("Location not found".into()),
-    )?;
+    let (location_booking, server_etag) = BookingManager::get_location_data(location_id, test_type)
+        .ok_or(ServerFnError::<NoCustomError>::ServerError(
+            "Location not found".into(),
+        ))?;
 
     if client_etag == server_etag {
         // WARN: for some reason this makes it open in hte browser
@@ -131,6 +156,9 @@ pub fn HomePage() -> impl IntoView {
 
     let (booking_etag, set_booking_etag) = create_signal(String::new());
 
+    let (test_type, set_test_type) = create_signal(TestType::Car);
+    let (test_types, set_test_types) = create_signal(vec![TestType::Car]);
+
     let (reset_sort_trigger, set_reset_sort_trigger) = create_signal(());
 
     let (announcement, set_announcement) = create_signal::<Option<String>>(None);
@@ -153,7 +181,9 @@ pub fn HomePage() -> impl IntoView {
         set_is_fetching_bookings(true);
 
         leptos::task::spawn_local(async move {
-            match get_location_bookings(booking_etag.get_untracked()).await {
+            match get_location_bookings(booking_etag.get_untracked(), test_type.get_untracked())
+                .await
+            {
                 Ok(data) => {
                     match data {
                         Some(data) => {
@@ -175,6 +205,35 @@ pub fn HomePage() -> impl IntoView {
     #[cfg(not(feature = "ssr"))]
     fetch_bookings();
 
+    #[cfg(not(feature = "ssr"))]
+    leptos::task::spawn_local(async move {
+        match get_test_types().await {
+            Ok(types) if !types.is_empty() => {
+                if !types.contains(&test_type.get_untracked()) {
+                    set_test_type(types[0]);
+                    set_booking_etag(String::new());
+                    set_bookings(Vec::new());
+                    fetch_bookings();
+                }
+                set_test_types(types);
+            }
+            Ok(_) => {}
+            Err(err) => {
+                leptos::logging::log!("Error fetching test types: {:?}", err);
+            }
+        }
+    });
+
+    let handle_test_type_change = move |ev: leptos::ev::Event| {
+        let Some(selected) = TestType::parse(&event_target_value(&ev)) else {
+            return;
+        };
+        set_test_type(selected);
+        set_booking_etag(String::new());
+        set_bookings(Vec::new());
+        fetch_bookings();
+    };
+
     #[cfg(not(feature = "ssr"))]
     Effect::new(move |_| {
         leptos::logging::log!("Setting up client-side refresh mechanism");
@@ -289,6 +348,23 @@ pub fn HomePage() -> impl IntoView {
 
             <div class="flex justify-between items-center mb-6">
                 <h2 class="text-2xl font-bold text-gray-800">NSW Available Drivers Tests</h2>
+                <div class="flex items-center gap-2">
+                    <label for="test-type" class="text-sm font-medium text-gray-700">Test:</label>
+                    <select
+                        id="test-type"
+                        class="px-2 py-1 border border-gray-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500"
+                        on:change=handle_test_type_change
+                    >
+                        {move || test_types.get().into_iter().map(|option| view! {
+                            <option
+                                value=option.as_str()
+                                selected=move || test_type.get() == option
+                            >
+                                {option.display()}
+                            </option>
+                        }).collect::<Vec<_>>()}
+                    </select>
+                </div>
             </div>
 
             <div class="mb-6">
@@ -379,6 +455,7 @@ pub fn HomePage() -> impl IntoView {
                 longitude=longitude
                 location_manager=location_manager.clone()
                 reset_sort_trigger=reset_sort_trigger
+                test_type=test_type
             />
 
             <div class="mt-6 flex justify-between items-center">
@@ -389,6 +466,14 @@ pub fn HomePage() -> impl IntoView {
                 </div>
 
                 <div class="flex gap-2">
+                    <a
+                        href="/api/feed.atom"
+                        target="_blank"
+                        class="px-3 py-1.5 bg-orange-500 text-white rounded-md hover:bg-orange-600 focus:outline-none focus:ring-2 focus:ring-orange-400 transition-colors inline-flex items-center justify-center gap-2"
+                    >
+                        <i class="fas fa-rss"></i>
+                        <span>Feed</span>
+                    </a>
                     <a
                         href="https://github.com/teehee567/nsw-drivers-test"
                         target="_blank"
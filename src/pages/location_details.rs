@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::time::Duration;
 
+use chrono::NaiveDate;
 use leptos::prelude::*;
 use leptos::server_fn::error::NoCustomError;
 use reqwest::header;
@@ -8,14 +9,18 @@ use serde::{Deserialize, Serialize};
 use web_sys::wasm_bindgen::prelude::Closure;
 
 use crate::data::location::LocationManager;
-use crate::data::shared_booking::TimeSlot;
+use crate::data::shared_booking::{SlotLabel, TestType, TimeSlot, SLOT_PARAM_FORMAT};
 use crate::utils::date::format_iso_date;
 use crate::utils::geocoding::geocode_address;
 
 use crate::pages::home::get_location_details;
 
 #[component]
-pub fn ExpandedLocationDetails(location_id: String, expanded: ReadSignal<bool>) -> impl IntoView {
+pub fn ExpandedLocationDetails(
+    location_id: String,
+    expanded: ReadSignal<bool>,
+    test_type: ReadSignal<TestType>,
+) -> impl IntoView {
     let (slots, set_slots) = create_signal(Vec::<TimeSlot>::new());
     let (is_loading, set_is_loading) = create_signal(false);
     let (error, set_error) = create_signal::<Option<String>>(None);
@@ -23,45 +28,27 @@ pub fn ExpandedLocationDetails(location_id: String, expanded: ReadSignal<bool>)
     let (location_etag, set_location_etag) = create_signal(String::new());
 
     let slots_by_date = create_memo(move |_| {
-        let mut grouped: HashMap<String, Vec<TimeSlot>> = HashMap::new();
+        let mut grouped: BTreeMap<NaiveDate, Vec<TimeSlot>> = BTreeMap::new();
 
         for slot in slots.get().iter() {
             if slot.availability {
-                if let Some(date_part) = slot.start_time.split_whitespace().next() {
-                    let entry = grouped
-                        .entry(date_part.to_string())
-                        .or_insert_with(Vec::new);
-                    entry.push(slot.clone());
-                }
+                grouped.entry(slot.date()).or_default().push(slot.clone());
             }
         }
 
-        let mut dates: Vec<_> = grouped.into_iter().collect();
-        dates.sort_by(|(date_a, _), (date_b, _)| {
-            let parts_a: Vec<&str> = date_a.split('/').collect();
-            let parts_b: Vec<&str> = date_b.split('/').collect();
-
-            if parts_a.len() == 3 && parts_b.len() == 3 {
-                let year_compare = parts_a[2].cmp(parts_b[2]);
-                if year_compare != std::cmp::Ordering::Equal {
-                    return year_compare;
-                }
-
-                let month_compare = parts_a[1].cmp(parts_b[1]);
-                if month_compare != std::cmp::Ordering::Equal {
-                    return month_compare;
-                }
-
-                return parts_a[0].cmp(parts_b[0]);
-            }
-
-            date_a.cmp(date_b)
-        });
-
-        dates
+        grouped
+            .into_values()
+            .map(|mut day_slots| {
+                day_slots.sort();
+                (day_slots[0].display_date(), day_slots)
+            })
+            .collect::<Vec<_>>()
     });
 
+    let calendar_location_id = location_id.clone();
+
     create_effect(move |_| {
+        let test_type = test_type.get();
         if expanded.get() {
             let location_id_clone = location_id.clone();
 
@@ -69,7 +56,13 @@ pub fn ExpandedLocationDetails(location_id: String, expanded: ReadSignal<bool>)
             set_error(None);
 
             leptos::task::spawn_local(async move {
-                match get_location_details(location_id_clone, location_etag.get_untracked()).await {
+                match get_location_details(
+                    location_id_clone,
+                    test_type,
+                    location_etag.get_untracked(),
+                )
+                .await
+                {
                     Ok(response) => match response {
                         Some(response) => {
                             set_slots(response.slots);
@@ -111,7 +104,25 @@ pub fn ExpandedLocationDetails(location_id: String, expanded: ReadSignal<bool>)
                             } else {
                                 view! {
                                     <div class="max-h-80 overflow-y-auto">
-                                        <h3 class="text-lg font-medium mb-2">Available Times</h3>
+                                        <div class="flex justify-between items-center mb-2">
+                                            <h3 class="text-lg font-medium">Available Times</h3>
+                                            <a
+                                                href=format!(
+                                                    "/api/calendar/{}.ics?test_type={}",
+                                                    calendar_location_id,
+                                                    test_type.get_untracked().as_str()
+                                                )
+                                                class="text-sm text-blue-600 hover:underline inline-flex items-center gap-1"
+                                                title="Subscribe to this centre's availability in your calendar app"
+                                            >
+                                                <i class="far fa-calendar"></i>
+                                                <span>Subscribe (.ics)</span>
+                                            </a>
+                                        </div>
+                                        <div class="flex gap-3 mb-2 text-xs text-gray-500">
+                                            <span><span class="inline-block w-2 h-2 rounded-full bg-purple-300 mr-1"></span>Likely cancellation</span>
+                                            <span><span class="inline-block w-2 h-2 rounded-full bg-blue-300 mr-1"></span>New release</span>
+                                        </div>
                                         <div class="space-y-4">
                                             {dates.into_iter().map(|(date, slots)| {
                                                 view! {
@@ -119,15 +130,35 @@ pub fn ExpandedLocationDetails(location_id: String, expanded: ReadSignal<bool>)
                                                         <h4 class="font-medium text-gray-700 mb-1">{date}</h4>
                                                         <div class="flex flex-wrap gap-2">
                                                             {slots.into_iter().map(|slot| {
-                                                                let time_only = slot.start_time
-                                                                    .split_whitespace()
-                                                                    .nth(1)
-                                                                    .unwrap_or(&slot.start_time)
-                                                                    .to_string();
+                                                                let time_only = slot.display_time();
+                                                                let (chip_class, title) = match slot.label {
+                                                                    Some(SlotLabel::LikelyCancellation) => ("inline-block bg-purple-100 text-purple-800 px-2 py-1 text-sm rounded", Some(SlotLabel::LikelyCancellation.display())),
+                                                                    Some(SlotLabel::NewRelease) => ("inline-block bg-blue-100 text-blue-800 px-2 py-1 text-sm rounded", Some(SlotLabel::NewRelease.display())),
+                                                                    None => ("inline-block bg-green-100 text-green-800 px-2 py-1 text-sm rounded", None),
+                                                                };
+
+                                                                let calendar_href = format!(
+                                                                    "/api/calendar/{}.ics?slot={}&test_type={}",
+                                                                    calendar_location_id,
+                                                                    slot.start_time.format(SLOT_PARAM_FORMAT),
+                                                                    test_type.get_untracked().as_str()
+                                                                );
 
                                                                 view! {
-                                                                    <span class="inline-block bg-green-100 text-green-800 px-2 py-1 text-sm rounded">
+                                                                    <span class=chip_class title=title>
                                                                         {time_only}
+                                                                        {(slot.label == Some(SlotLabel::LikelyCancellation)).then(|| view! {
+                                                                            <i class="fas fa-bolt ml-1 text-xs"></i>
+                                                                        })}
+                                                                        <a
+                                                                            href=calendar_href
+                                                                            download=""
+                                                                            class="ml-1 opacity-60 hover:opacity-100"
+                                                                            title="Add this slot to your calendar"
+                                                                            on:click=|ev| ev.stop_propagation()
+                                                                        >
+                                                                            <i class="far fa-calendar-plus text-xs"></i>
+                                                                        </a>
                                                                     </span>
                                                                 }
                                                             }).collect::<Vec<_>>()}
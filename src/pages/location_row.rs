@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 use web_sys::wasm_bindgen::prelude::Closure;
 
 use crate::data::location::LocationManager;
-use crate::data::shared_booking::TimeSlot;
+use crate::data::shared_booking::{SlotLabel, TestType, TimeSlot};
 use crate::utils::date::format_iso_date;
 use crate::utils::geocoding::geocode_address;
 
@@ -19,7 +19,11 @@ pub fn LocationRow(
     loc: crate::data::location::Location,
     distance: f64,
     earliest_slot: Option<TimeSlot>,
+    last_scraped: Option<String>,
+    stale: bool,
+    likely_cancellations: usize,
     is_loading: ReadSignal<bool>,
+    test_type: ReadSignal<TestType>,
 ) -> impl IntoView {
     let (expanded, set_expanded) = create_signal(false);
 
@@ -27,6 +31,10 @@ pub fn LocationRow(
         set_expanded.update(|val| *val = !*val);
     };
 
+    let earliest_is_cancellation = earliest_slot
+        .as_ref()
+        .is_some_and(|slot| slot.label == Some(SlotLabel::LikelyCancellation));
+
     let total_tests = loc.passes + loc.failures;
     let low_data = total_tests < 1000;
 
@@ -46,7 +54,17 @@ pub fn LocationRow(
                 <td class="px-1 py-3 md:px-3 md:py-3 whitespace-nowrap text-sm text-gray-500">
                     {match earliest_slot {
                         Some(slot) => view! {
-                            <span class="text-green-600 font-medium">{slot.start_time}</span>
+                            <span class={if stale { "text-gray-500 font-medium" } else { "text-green-600 font-medium" }}>
+                                {slot.display()}
+                            </span>
+                            {(slot.label == Some(SlotLabel::LikelyCancellation)).then(|| view! {
+                                <span
+                                    class="ml-1 px-1 py-0.5 rounded text-xs bg-purple-100 text-purple-700"
+                                    title="Appeared earlier than this centre's previous earliest slot"
+                                >
+                                    Cancellation
+                                </span>
+                            })}
                         }.into_any(),
                         None => {
                             if is_loading.get_untracked() {
@@ -56,6 +74,27 @@ pub fn LocationRow(
                             }
                         }
                     }}
+                    {(!earliest_is_cancellation && likely_cancellations > 0).then(|| view! {
+                        <span class="ml-1 text-xs text-purple-600">
+                            {format!("{} cancellation{}", likely_cancellations, if likely_cancellations == 1 { "" } else { "s" })}
+                        </span>
+                    })}
+                    {if stale {
+                        let title = match last_scraped {
+                            Some(time) => format!("Last checked {}", format_iso_date(&time)),
+                            None => "Not checked in the latest update".to_string(),
+                        };
+                        view! {
+                            <span
+                                class="ml-1 px-1 py-0.5 rounded text-xs bg-amber-100 text-amber-700"
+                                title=title
+                            >
+                                Stale
+                            </span>
+                        }.into_any()
+                    } else {
+                        view! { <span></span> }.into_any()
+                    }}
                 </td>
 
                 <td class="px-1 py-3 md:px-3 md:py-3 whitespace-nowrap text-sm text-gray-500">
@@ -134,6 +173,7 @@ pub fn LocationRow(
             <ExpandedLocationDetails
                 location_id=loc.id.to_string()
                 expanded=expanded
+                test_type=test_type
             />
         </>
     }
@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 use web_sys::wasm_bindgen::prelude::Closure;
 
 use crate::data::location::LocationManager;
-use crate::data::shared_booking::TimeSlot;
+use crate::data::shared_booking::{TestType, TimeSlot};
 use crate::utils::date::format_iso_date;
 use crate::utils::geocoding::geocode_address;
 
@@ -76,6 +76,17 @@ fn SortableHeader(
     }
 }
 
+/// Export endpoint URL including distances from the searched coordinates.
+fn export_url(format: &str, latitude: f64, longitude: f64, test_type: TestType) -> String {
+    format!(
+        "/api/export.{}?lat={}&lng={}&test_type={}",
+        format,
+        latitude,
+        longitude,
+        test_type.as_str()
+    )
+}
+
 #[component]
 pub fn LocationsTable(
     bookings: ReadSignal<Vec<LocationBookingViewModel>>,
@@ -84,13 +95,14 @@ pub fn LocationsTable(
     longitude: ReadSignal<f64>,
     location_manager: LocationManager,
     reset_sort_trigger: ReadSignal<()>,
+    test_type: ReadSignal<TestType>,
 ) -> impl IntoView {
     let booking_map = create_memo(move |_| {
         bookings
             .get()
             .into_iter()
-            .map(|booking| (booking.location.clone(), booking.earliest_slot))
-            .collect::<HashMap<String, Option<TimeSlot>>>()
+            .map(|booking| (booking.location.clone(), booking))
+            .collect::<HashMap<String, LocationBookingViewModel>>()
     });
 
     let (sort_column, set_sort_column) = create_signal(SortColumn::Distance);
@@ -128,8 +140,12 @@ pub fn LocationsTable(
             .into_iter()
             .map(|(loc, distance)| {
                 let location_id = loc.id.to_string();
-                let earliest_slot = booking_data.get(&location_id).cloned().flatten();
-                (loc, distance, earliest_slot)
+                let booking = booking_data.get(&location_id);
+                let earliest_slot = booking.and_then(|b| b.earliest_slot.clone());
+                let last_scraped = booking.and_then(|b| b.last_scraped.clone());
+                let stale = booking.is_some_and(|b| b.stale);
+                let likely_cancellations = booking.map_or(0, |b| b.likely_cancellations);
+                (loc, distance, earliest_slot, last_scraped, stale, likely_cancellations)
             })
             .collect();
 
@@ -176,6 +192,25 @@ pub fn LocationsTable(
                 </svg>
                 <span>Click on any row to view available time slots for that location</span>
             </div>
+            <div class="flex justify-end items-center gap-2 mb-2 text-sm">
+                <span class="text-gray-500">Download:</span>
+                <a
+                    href=move || export_url("csv", latitude.get(), longitude.get(), test_type.get())
+                    download="availability.csv"
+                    class="px-2 py-1 border border-gray-300 rounded-md text-gray-700 hover:bg-gray-100 transition-colors inline-flex items-center gap-1"
+                >
+                    <i class="fas fa-file-csv"></i>
+                    <span>CSV</span>
+                </a>
+                <a
+                    href=move || export_url("json", latitude.get(), longitude.get(), test_type.get())
+                    download="availability.json"
+                    class="px-2 py-1 border border-gray-300 rounded-md text-gray-700 hover:bg-gray-100 transition-colors inline-flex items-center gap-1"
+                >
+                    <i class="fas fa-file-code"></i>
+                    <span>JSON</span>
+                </a>
+            </div>
             <div class="overflow-x-auto">
                 <table class="min-w-full bg-white border border-gray-200 rounded-lg overflow-hidden table-fixed">
                     <colgroup>
@@ -228,13 +263,17 @@ pub fn LocationsTable(
                         {move || {
                             let locations_data = sorted_locations.get();
 
-                            locations_data.into_iter().map(|(loc, distance, earliest_slot)| {
+                            locations_data.into_iter().map(|(loc, distance, earliest_slot, last_scraped, stale, likely_cancellations)| {
                                 view! {
                                     <LocationRow
                                         loc=loc
                                         distance=distance
                                         earliest_slot=earliest_slot
+                                        last_scraped=last_scraped
+                                        stale=stale
+                                        likely_cancellations=likely_cancellations
                                         is_loading=is_loading
+                                        test_type=test_type
                                     />
                                 }
                             }).collect::<Vec<_>>()
@@ -1,10 +1,12 @@
 use dotenv::dotenv;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
+use crate::data::shared_booking::TestType;
+
 #[derive(Deserialize, Clone)]
 pub struct Settings {
     pub headless: bool,
@@ -13,14 +15,281 @@ pub struct Settings {
     pub have_booking: bool,
     pub element_timeout: u64,
     pub retries: u64,
+    /// Delay before the second scrape attempt of a run, doubled for each further attempt.
+    #[serde(default = "default_retry_delay_secs")]
+    pub retry_delay_secs: u64,
     pub scrape_refresh_time_min: u64,
     pub proxy_path: String,
     pub parallel_browsers: usize,
     pub scraping_enabled: bool,
+    /// Tests to scrape availability for, each as its own run. Backends that scrape RTA only
+    /// know the car test's booking flow, so rider and heavy vehicle data only comes from the
+    /// demo and replay backends until their selectors are recorded.
+    #[serde(default = "default_test_types")]
+    pub test_types: Vec<TestType>,
+    /// Refresh intervals and priorities for groups of locations. Locations not listed here
+    /// are refreshed every `scrape_refresh_time_min`.
+    #[serde(default)]
+    pub location_schedules: Vec<LocationSchedule>,
+    /// Caps how many due locations go into one run, highest priority first.
+    #[serde(default)]
+    pub max_locations_per_run: Option<usize>,
+    /// Cron expression (with seconds, Sydney time) for when runs start. Without it a run
+    /// starts as soon as a location is due.
+    #[serde(default)]
+    pub scrape_schedule: Option<String>,
+    /// Windows, in Sydney time, during which no run starts.
+    #[serde(default)]
+    pub quiet_hours: Vec<QuietHours>,
+    /// Random delay of up to this many minutes added to each planned run.
+    #[serde(default)]
+    pub schedule_jitter_min: u64,
+    /// Failed runs in a row before the circuit breaker opens.
+    #[serde(default = "default_breaker_failure_threshold")]
+    pub breaker_failure_threshold: u32,
+    /// Share of proxies blocked in a run at which the run counts as failed.
+    #[serde(default = "default_breaker_block_rate")]
+    pub breaker_block_rate: f64,
+    /// First backoff once the breaker opens, doubled each time it reopens.
+    #[serde(default = "default_breaker_backoff_min")]
+    pub breaker_backoff_min: u64,
+    #[serde(default = "default_breaker_max_backoff_min")]
+    pub breaker_max_backoff_min: u64,
     #[serde(default)]
     pub webhook_url: Option<String>,
     #[serde(default)]
     pub initial_delay_hours: f64,
+    #[serde(default = "default_history_path")]
+    pub history_path: String,
+    /// JSON lines log of scrape run reports.
+    #[serde(default = "default_runs_path")]
+    pub runs_path: String,
+    /// JSON lines archive of the raw timeslots payloads RTA returned. Unset to stop archiving.
+    #[serde(default = "default_payload_archive_path")]
+    pub payload_archive_path: Option<String>,
+    /// Days of payloads the archive keeps. Unset to keep them all.
+    #[serde(default = "default_payload_retention_days")]
+    pub payload_retention_days: Option<u64>,
+    /// Where screenshots and HTML of failed locations are saved, one directory per run. These
+    /// pages belong to the logged in account and can show its name and booking details, so
+    /// treat the directory as personal data. Login and booking page failures only keep a URL.
+    #[serde(default = "default_artifacts_dir")]
+    pub artifacts_dir: String,
+    /// How many runs' artifacts to keep. 0 turns capturing off.
+    #[serde(default = "default_artifact_retention_runs")]
+    pub artifact_retention_runs: usize,
+    #[serde(default)]
+    pub storage_backend: StorageBackend,
+    /// Defaults to `data/bookings.json` or `data/bookings.db` depending on the backend.
+    #[serde(default)]
+    pub storage_path: Option<String>,
+    #[serde(default)]
+    pub scraper_backend: ScraperBackend,
+    /// Directory the replay backend reads fixtures from and `record_fixtures` writes to.
+    #[serde(default = "default_fixtures_dir")]
+    pub fixtures_dir: String,
+    #[serde(default)]
+    pub record_fixtures: bool,
+    /// The demo backend keeps its bookings, history and run log here instead of next to the
+    /// real ones, so it can't overwrite them.
+    #[serde(default = "default_demo_data_dir")]
+    pub demo_data_dir: String,
+    /// Interpreter the subprocess backend runs `scraper.py` with.
+    #[serde(default = "default_python_path")]
+    pub python_path: String,
+    /// Hard limit on a whole subprocess scrape, restarts included.
+    #[serde(default = "default_scrape_timeout_min")]
+    pub scrape_timeout_min: u64,
+    /// How long the subprocess may go without reporting progress on a location before it is
+    /// considered hung and killed.
+    #[serde(default = "default_location_timeout_secs")]
+    pub location_timeout_secs: u64,
+    /// Times a killed or crashed subprocess is restarted for the locations it didn't finish.
+    #[serde(default = "default_scraper_restarts")]
+    pub scraper_restarts: u32,
+    /// Shared secret workers authenticate with. The worker endpoints are off without it.
+    #[serde(default)]
+    pub worker_token: Option<String>,
+    /// Locations per job handed to a worker by the `workers` backend.
+    #[serde(default = "default_worker_batch_size")]
+    pub worker_batch_size: usize,
+    /// How long a worker holds a job without renewing before it goes to another worker.
+    #[serde(default = "default_job_lease_secs")]
+    pub job_lease_secs: u64,
+    /// Primary instance to lease jobs from in worker mode.
+    #[serde(default)]
+    pub primary_url: Option<String>,
+    /// Name the worker reports to the primary. Defaults to the host name.
+    #[serde(default)]
+    pub worker_name: Option<String>,
+    /// How often an idle worker asks for a job.
+    #[serde(default = "default_worker_poll_secs")]
+    pub worker_poll_secs: u64,
+    #[serde(default = "default_proxy_state_path")]
+    pub proxy_state_path: String,
+    /// Optional URL serving an extra newline separated proxy list, merged with `proxy_path`.
+    #[serde(default)]
+    pub proxy_source_url: Option<String>,
+    #[serde(default = "default_proxy_source_refresh_min")]
+    pub proxy_source_refresh_min: u64,
+    /// Bearer token for the `/api/status` endpoints. They are off without it.
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Public address of the site, used for links in feeds.
+    #[serde(default = "default_site_url")]
+    pub site_url: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct LocationSchedule {
+    /// A label for the group, e.g. a region, used in logs.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub locations: Vec<String>,
+    pub refresh_min: u64,
+    /// Higher priorities are scraped first.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// A daily window such as `01:00`-`05:00`. Windows where `end` is before `start` wrap past
+/// midnight.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScraperBackend {
+    /// `scraper.py` run through pyo3.
+    #[default]
+    Python,
+    /// `scraper.py` run as a supervised child process.
+    Subprocess,
+    /// Recorded fixtures from `fixtures_dir`, no browser or network needed.
+    Replay,
+    /// Synthetic, evolving availability for every centre.
+    Demo,
+    /// Jobs leased to workers running in worker mode.
+    Workers,
+}
+
+impl ScraperBackend {
+    /// Whether this backend logs in to RTA.
+    pub fn is_live(&self) -> bool {
+        matches!(self, ScraperBackend::Python | ScraperBackend::Subprocess)
+    }
+
+    /// Whether this backend scrapes through a browser, which limits the proxies it can use.
+    pub fn uses_browser(&self) -> bool {
+        matches!(self, ScraperBackend::Python | ScraperBackend::Subprocess)
+    }
+}
+
+fn default_retry_delay_secs() -> u64 {
+    5
+}
+
+fn default_test_types() -> Vec<TestType> {
+    vec![TestType::Car]
+}
+
+fn default_python_path() -> String {
+    "python3".to_string()
+}
+
+fn default_scrape_timeout_min() -> u64 {
+    60
+}
+
+fn default_location_timeout_secs() -> u64 {
+    300
+}
+
+fn default_scraper_restarts() -> u32 {
+    1
+}
+
+fn default_fixtures_dir() -> String {
+    "dev/fixtures".to_string()
+}
+
+fn default_demo_data_dir() -> String {
+    "data/demo".to_string()
+}
+
+fn default_history_path() -> String {
+    "data/history.jsonl".to_string()
+}
+
+fn default_runs_path() -> String {
+    "data/runs.jsonl".to_string()
+}
+
+fn default_payload_archive_path() -> Option<String> {
+    Some("data/payloads.jsonl".to_string())
+}
+
+fn default_payload_retention_days() -> Option<u64> {
+    Some(90)
+}
+
+fn default_artifacts_dir() -> String {
+    "data/artifacts".to_string()
+}
+
+fn default_artifact_retention_runs() -> usize {
+    20
+}
+
+fn default_worker_batch_size() -> usize {
+    5
+}
+
+fn default_job_lease_secs() -> u64 {
+    900
+}
+
+fn default_worker_poll_secs() -> u64 {
+    30
+}
+
+fn default_proxy_state_path() -> String {
+    "data/proxy_state.json".to_string()
+}
+
+fn default_proxy_source_refresh_min() -> u64 {
+    60
+}
+
+fn default_breaker_failure_threshold() -> u32 {
+    3
+}
+
+fn default_breaker_block_rate() -> f64 {
+    0.5
+}
+
+fn default_breaker_backoff_min() -> u64 {
+    30
+}
+
+fn default_breaker_max_backoff_min() -> u64 {
+    24 * 60
+}
+
+fn default_site_url() -> String {
+    "https://driverstest.noob.place".to_string()
 }
 
 impl Settings {
@@ -33,14 +302,67 @@ impl Settings {
 
         let mut settings: Settings = serde_yaml::from_str(&contents)?;
 
-        settings.username = parse_env_var(&settings.username)?;
-        settings.password = parse_env_var(&settings.password)?;
-        settings.proxy_path = parse_env_var(&settings.proxy_path)?;
+        if let Ok(backend) = env::var("SCRAPER_BACKEND") {
+            settings.scraper_backend = serde_yaml::from_str(&backend)
+                .map_err(|e| format!("Invalid SCRAPER_BACKEND '{}': {}", backend, e))?;
+        }
+
+        // Only the car test's booking flow has been checked against the live site.
+        let scrapes_rta = settings.scraper_backend.is_live()
+            || settings.scraper_backend == ScraperBackend::Workers;
+        if scrapes_rta {
+            if let Some(unverified) = settings
+                .test_types
+                .iter()
+                .find(|test_type| **test_type != TestType::Car)
+            {
+                return Err(format!(
+                    "test_types: {} is not verified against RTA yet, only car is supported",
+                    unverified.as_str()
+                )
+                .into());
+            }
+        }
+
+        if settings.scraper_backend.is_live() {
+            settings.username = parse_env_var(&settings.username)?;
+            settings.password = parse_env_var(&settings.password)?;
+            settings.proxy_path = parse_env_var(&settings.proxy_path)?;
+        } else {
+            // Offline backends never log in, so missing credentials are fine.
+            settings.username = parse_env_var(&settings.username).unwrap_or_default();
+            settings.password = parse_env_var(&settings.password).unwrap_or_default();
+            settings.proxy_path = parse_env_var(&settings.proxy_path).unwrap_or_default();
+        }
+
+        if let Some(ref storage_path) = settings.storage_path {
+            settings.storage_path = Some(parse_env_var(storage_path)?);
+        }
         
+        if let Some(ref proxy_source_url) = settings.proxy_source_url {
+            settings.proxy_source_url = Some(parse_env_var(proxy_source_url)?);
+        }
+
+        if let Some(ref admin_token) = settings.admin_token {
+            settings.admin_token = Some(parse_env_var(admin_token)?);
+        }
+
+        if let Some(ref worker_token) = settings.worker_token {
+            settings.worker_token = Some(parse_env_var(worker_token)?);
+        }
+
+        if let Some(ref primary_url) = settings.primary_url {
+            settings.primary_url = Some(parse_env_var(primary_url)?);
+        }
+
         if let Some(ref webhook_url) = settings.webhook_url {
             settings.webhook_url = Some(parse_env_var(webhook_url)?);
         }
 
+        if settings.scraper_backend == ScraperBackend::Demo {
+            settings.use_demo_data_dir();
+        }
+
         if let Ok(delay_str) = env::var("INITIAL_DELAY_HOURS") {
             if let Ok(delay) = delay_str.parse::<f64>() {
                 settings.initial_delay_hours = delay;
@@ -50,32 +372,40 @@ impl Settings {
         Ok(settings)
     }
 
-    pub fn read_proxies(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let proxy_contents = std::fs::read_to_string(&self.proxy_path)
-            .map_err(|e| format!("Failed to read proxy file '{}': {}", self.proxy_path, e))?;
-        let proxies: Vec<String> = proxy_contents
-            .lines()
-            .map(|line| line.trim().to_string())
-            .filter(|line| !line.is_empty())
-            .collect();
-        Ok(proxies)
+    /// The schedule covering `location`, if any. The first matching entry wins.
+    pub fn location_schedule(&self, location: &str) -> Option<&LocationSchedule> {
+        self.location_schedules
+            .iter()
+            .find(|schedule| schedule.locations.iter().any(|id| id == location))
     }
 
-    pub fn get_proxies_from_index(&self, start_index: usize, count: usize) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let all_proxies = self.read_proxies()?;
-        if all_proxies.is_empty() {
-            return Ok(vec![]);
-        }
-        
-        let len = all_proxies.len();
-        let mut result = Vec::with_capacity(count.min(len));
-        
-        for i in 0..count.min(len) {
-            let idx = (start_index + i) % len;
-            result.push(all_proxies[idx].clone());
+    /// Moves every data file the demo backend writes into `demo_data_dir`, keeping the file
+    /// names. Demo availability is synthetic and must never end up in the real data.
+    fn use_demo_data_dir(&mut self) {
+        let demo_path = |path: &str| {
+            let file_name = Path::new(path)
+                .file_name()
+                .map(|name| name.to_os_string())
+                .unwrap_or_default();
+            Path::new(&self.demo_data_dir)
+                .join(file_name)
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        self.storage_path = Some(demo_path(&self.storage_path()));
+        self.history_path = demo_path(&self.history_path);
+        self.runs_path = demo_path(&self.runs_path);
+        self.artifacts_dir = demo_path(&self.artifacts_dir);
+        self.payload_archive_path = None;
+    }
+
+    pub fn storage_path(&self) -> String {
+        match (&self.storage_path, self.storage_backend) {
+            (Some(path), _) => path.clone(),
+            (None, StorageBackend::Json) => "data/bookings.json".to_string(),
+            (None, StorageBackend::Sqlite) => "data/bookings.db".to_string(),
         }
-        
-        Ok(result)
     }
 }
 